@@ -0,0 +1,64 @@
+// examples/embedded.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! Enciphers a buffer without the standard library or an allocator. Building
+//! it for a bare-metal target checks that the machine still fits there:
+//!
+//! ```text
+//! cargo build -p enigma-core --example embedded --no-default-features \
+//!     --target thumbv7em-none-eabihf
+//! ```
+//!
+//! On a hosted target it is an ordinary program, so `cargo test` can build it
+//! whichever features are on.
+
+#![cfg_attr(target_os = "none", no_std, no_main)]
+
+use enigma_core::reflectors::ReflectorB;
+use enigma_core::rotors::{RotorEncode, RotorI, RotorII, RotorIII};
+use enigma_core::{plugboard, ArmyEnigma, Enigma};
+
+pub fn encipher(buffer: &mut [u8]) {
+    let mut machine = ArmyEnigma::new(
+        RotorI::new('A', 'A'),
+        RotorII::new('A', 'A'),
+        RotorIII::new('A', 'A'),
+        ReflectorB {},
+        plugboard!("AV BS CG DL FU HZ IN KM OW RX"),
+    );
+
+    for byte in buffer.iter_mut() {
+        if let Ok(output) = machine.keypress(*byte as char) {
+            *byte = output as u8;
+        }
+    }
+}
+
+#[cfg(target_os = "none")]
+#[no_mangle]
+pub extern "C" fn enigma_demo() -> u8 {
+    let mut buffer = *b"AAAAA";
+    encipher(&mut buffer);
+
+    buffer[0]
+}
+
+#[cfg(target_os = "none")]
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+#[cfg(not(target_os = "none"))]
+fn main() {
+    let mut buffer = *b"AAAAA";
+    encipher(&mut buffer);
+
+    println!("{}", String::from_utf8_lossy(&buffer));
+}
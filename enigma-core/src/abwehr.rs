@@ -0,0 +1,461 @@
+// src/abwehr.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! The Abwehr Enigma G. Its rotors are driven by cog wheels rather than
+//! pawls, so each one carries into the next like an odometer, with no
+//! double step; the reflector sits at the end of the chain and turns too.
+
+use crate::entry::{EntryWheel, QwertzEntry};
+use crate::permutation::Permutation;
+use crate::reflectors::ReflectorError;
+use crate::reflectors::{settable_reflector, SettableReflector};
+use crate::rotors::RotorEncode;
+use crate::stepping::{Cog, ReflectorWheel, Stepping};
+use crate::{_check_input, _set_positions, _set_ring_settings, Enigma, EnigmaError};
+
+#[cfg(feature = "alloc")]
+use crate::settings::{MachineSettings, RotorSettings};
+#[cfg(feature = "alloc")]
+use crate::snapshot::{ReflectorState, RotorState, Snapshot, SnapshotError};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use enigma_macros::RotorEncode;
+
+#[derive(RotorEncode)]
+#[name(I)]
+#[key_ordering(DMTWSILRUYQNKFEJCAZBPGXOHV)]
+#[notches(SUVWZABCEFGIKLOPQ)]
+pub struct RotorG312I {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(II)]
+#[key_ordering(HQZGPJTMOBLNCIFDYAWVEUSRKX)]
+#[notches(STVYZACDFGHKMNQ)]
+pub struct RotorG312II {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(III)]
+#[key_ordering(UQNTLSZFMREHDPXKIBVYGJCWOA)]
+#[notches(UWXAEFHKMNR)]
+pub struct RotorG312III {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+settable_reflector!(ReflectorG312, b"RULQMZJSYGOCETKWDAHNBXPVIF");
+
+#[derive(RotorEncode)]
+#[name(I)]
+#[key_ordering(RCSPBLKQAUMHWYTIFZVGOJNEXD)]
+#[notches(SUVWZABCEFGIKLOPQ)]
+pub struct RotorG260I {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(II)]
+#[key_ordering(WCMIBVPJXAROSGNDLZKEYHUFQT)]
+#[notches(STVYZACDFGHKMNQ)]
+pub struct RotorG260II {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(III)]
+#[key_ordering(FVDHZELSQMAXOKYIWPGCBUJTNR)]
+#[notches(UWXAEFHKMNR)]
+pub struct RotorG260III {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+settable_reflector!(ReflectorG260, b"IMETCGFRAYSQBZXWLHKDVUPOJN");
+
+pub struct AbwehrEnigma<A, B, C, D> {
+    rotor1: A,
+    rotor2: B,
+    rotor3: C,
+    reflector: D,
+    reflector_position: char,
+}
+
+impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: SettableReflector>
+    AbwehrEnigma<A, B, C, D>
+{
+    pub fn new(rotor1: A, rotor2: B, rotor3: C, reflector: D) -> Self {
+        AbwehrEnigma {
+            rotor1,
+            rotor2,
+            rotor3,
+            reflector_position: reflector.position(),
+            reflector,
+        }
+    }
+
+    pub fn reflector_position(&self) -> char {
+        self.reflector.position()
+    }
+}
+
+impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: SettableReflector> Enigma
+    for AbwehrEnigma<A, B, C, D>
+{
+    fn reset(&mut self) {
+        self.rotor1 = A::new(self.rotor1.ring_setting(), self.rotor1.init_position());
+        self.rotor2 = B::new(self.rotor2.ring_setting(), self.rotor2.init_position());
+        self.rotor3 = C::new(self.rotor3.ring_setting(), self.rotor3.init_position());
+        self.reflector
+            .set_position(self.reflector_position)
+            .expect("reflector position was valid when the machine was built");
+    }
+
+    fn keypress(&mut self, input: char) -> Result<char, EnigmaError> {
+        _check_input(input)?;
+
+        // The reflector is the last link in the gear train.
+        Cog.step(&mut [
+            &mut ReflectorWheel(&mut self.reflector),
+            &mut self.rotor1,
+            &mut self.rotor2,
+            &mut self.rotor3,
+        ]);
+
+        let output = QwertzEntry.transpose_in(input);
+        let output = self.rotor3.transpose_in(output);
+        let output = self.rotor2.transpose_in(output);
+        let output = self.rotor1.transpose_in(output);
+        let output = self.reflector.transpose(output);
+        let output = self.rotor1.transpose_out(output);
+        let output = self.rotor2.transpose_out(output);
+        let output = self.rotor3.transpose_out(output);
+        let output = QwertzEntry.transpose_out(output);
+
+        Ok(output)
+    }
+
+    /// There is no plugboard on the G.
+    fn plugboard_transpose(&self, input: char) -> char {
+        input
+    }
+
+    /// The reflector turns, so its position leads the rotor positions.
+    #[cfg(feature = "alloc")]
+    fn settings(&self) -> Vec<char> {
+        vec![
+            self.reflector.position(),
+            self.rotor1.position(),
+            self.rotor2.position(),
+            self.rotor3.position(),
+        ]
+    }
+
+    fn set_ring_settings(&mut self, ring_settings: &[char]) -> Result<(), EnigmaError> {
+        _set_ring_settings(
+            &mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3],
+            ring_settings,
+        )
+    }
+
+    /// The reflector's position leads, as in [`Enigma::settings`].
+    fn set_positions(&mut self, positions: &[char]) -> Result<(), EnigmaError> {
+        let (reflector_position, rotor_positions) = match positions {
+            [reflector_position, rotor_positions @ ..] if positions.len() == 4 => {
+                (*reflector_position, rotor_positions)
+            }
+            _ => {
+                return Err(EnigmaError::RotorCount {
+                    expected: 4,
+                    found: positions.len(),
+                })
+            }
+        };
+
+        if !reflector_position.is_ascii_uppercase() {
+            return Err(ReflectorError::InvalidPosition(reflector_position).into());
+        }
+
+        _set_positions(
+            &mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3],
+            rotor_positions,
+        )?;
+        self.reflector.set_position(reflector_position)?;
+        self.reflector_position = reflector_position;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn machine_settings(&self) -> MachineSettings {
+        MachineSettings::with_settable_reflector(
+            &self.reflector,
+            vec![
+                RotorSettings::of(&self.rotor1),
+                RotorSettings::of(&self.rotor2),
+                RotorSettings::of(&self.rotor3),
+            ],
+        )
+    }
+
+    fn permutation(&self) -> Permutation {
+        let entry = QwertzEntry.permutation();
+        let rotors = self
+            .rotor3
+            .permutation()
+            .then(&self.rotor2.permutation())
+            .then(&self.rotor1.permutation());
+
+        entry
+            .then(&rotors)
+            .then(&self.reflector.permutation())
+            .then(&rotors.inverse())
+            .then(&entry.inverse())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rotors: vec![
+                RotorState::of(&self.rotor1),
+                RotorState::of(&self.rotor2),
+                RotorState::of(&self.rotor3),
+            ],
+            reflector: Some(ReflectorState {
+                init_position: self.reflector_position,
+                position: self.reflector.position(),
+            }),
+            stecker: None,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), EnigmaError> {
+        snapshot.check(
+            &[self.rotor1.name(), self.rotor2.name(), self.rotor3.name()],
+            true,
+        )?;
+        if snapshot.stecker.is_some_and(|s| !s.is_identity()) {
+            return Err(SnapshotError::Stecker.into());
+        }
+
+        let reflector = snapshot.reflector.expect("checked above");
+        self.reflector.set_position(reflector.position)?;
+        self.reflector_position = reflector.init_position;
+        snapshot.rotors[0].apply(&mut self.rotor1);
+        snapshot.rotors[1].apply(&mut self.rotor2);
+        snapshot.rotors[2].apply(&mut self.rotor3);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::reflectors::Reflector;
+    #[cfg(feature = "alloc")]
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_notch_counts() {
+        let count = |at_notch: &dyn Fn(char) -> bool| ('A'..='Z').filter(|c| at_notch(*c)).count();
+
+        assert_eq!(count(&|c| RotorG312I::new('A', c).at_notch()), 17);
+        assert_eq!(count(&|c| RotorG312II::new('A', c).at_notch()), 15);
+        assert_eq!(count(&|c| RotorG312III::new('A', c).at_notch()), 11);
+    }
+
+    // Regression vectors for this and the G-260 test, worked out by a
+    // separate script from the same wiring tables. They are not published
+    // traffic, so they pin the current behaviour rather than prove it right.
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_g312_regression() {
+        let mut machine = AbwehrEnigma::new(
+            RotorG312I::new('A', 'A'),
+            RotorG312II::new('A', 'A'),
+            RotorG312III::new('A', 'A'),
+            ReflectorG312::default(),
+        );
+
+        assert_eq!(machine.encipher_str("AAAAAAAAAA").unwrap(), "EBTMYLCEYW");
+        assert_eq!(machine.settings(), vec!['D', 'D', 'E', 'K']);
+
+        machine.reset();
+        assert_eq!(machine.settings(), vec!['A', 'A', 'A', 'A']);
+        assert_eq!(machine.encipher_str("EBTMYLCEYW").unwrap(), "AAAAAAAAAA");
+
+        let mut machine = AbwehrEnigma::new(
+            RotorG312III::new('B', 'Q'),
+            RotorG312I::new('Z', 'P'),
+            RotorG312II::new('J', 'M'),
+            ReflectorG312::new('K').unwrap(),
+        );
+
+        assert_eq!(
+            machine.encipher_str("ABWEHRGEHEIMSACHE").unwrap(),
+            "VGUDCJYMDNFTHYAEI"
+        );
+        assert_eq!(machine.settings(), vec!['M', 'W', 'Z', 'D']);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_g260_regression() {
+        let mut machine = AbwehrEnigma::new(
+            RotorG260I::new('A', 'A'),
+            RotorG260II::new('A', 'A'),
+            RotorG260III::new('A', 'A'),
+            ReflectorG260::default(),
+        );
+
+        assert_eq!(machine.encipher_str("AAAAAAAAAA").unwrap(), "FLXGEJVNVM");
+
+        let mut machine = AbwehrEnigma::new(
+            RotorG260II::new('C', 'O'),
+            RotorG260III::new('R', 'R'),
+            RotorG260I::new('T', 'P'),
+            ReflectorG260::new('Y').unwrap(),
+        );
+
+        assert_eq!(
+            machine.encipher_str("BUENOSAIRESMELDUNG").unwrap(),
+            "VCGMUHZNYKKRDHWPMT"
+        );
+        assert_eq!(machine.settings(), vec!['A', 'T', 'E', 'H']);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_no_double_step() {
+        // The middle rotor sits on a notch, but the right one does not: a pawl
+        // machine would double-step here, the cogs leave it alone.
+        let mut machine = AbwehrEnigma::new(
+            RotorG312I::new('A', 'A'),
+            RotorG312II::new('A', 'S'),
+            RotorG312III::new('A', 'B'),
+            ReflectorG312::default(),
+        );
+
+        machine.keypress('A').unwrap();
+        assert_eq!(machine.settings(), vec!['A', 'A', 'S', 'C']);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_reflector_turns() {
+        // Every rotor on a notch carries all the way through to the reflector.
+        let mut machine = AbwehrEnigma::new(
+            RotorG312I::new('A', 'Q'),
+            RotorG312II::new('A', 'Q'),
+            RotorG312III::new('A', 'R'),
+            ReflectorG312::new('Z').unwrap(),
+        );
+
+        machine.keypress('A').unwrap();
+        assert_eq!(machine.settings(), vec!['A', 'R', 'R', 'S']);
+        assert_eq!(machine.reflector_position(), 'A');
+
+        for position in 'A'..='Z' {
+            let reflector = ReflectorG312::new(position).unwrap();
+            assert!(reflector.permutation().is_involution());
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_snapshot_restore() {
+        let mut machine = AbwehrEnigma::new(
+            RotorG312I::new('A', 'Q'),
+            RotorG312II::new('A', 'Q'),
+            RotorG312III::new('A', 'R'),
+            ReflectorG312::new('Z').unwrap(),
+        );
+
+        machine.keypress('A').unwrap();
+        let checkpoint = machine.snapshot();
+        let first = machine.encipher_str("GEHEIM").unwrap();
+
+        machine.restore(&checkpoint).unwrap();
+        assert_eq!(machine.reflector_position(), 'A');
+        assert_eq!(machine.encipher_str("GEHEIM").unwrap(), first);
+
+        machine.reset();
+        assert_eq!(machine.settings(), vec!['Z', 'Q', 'Q', 'R']);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_machine_settings() {
+        let machine = AbwehrEnigma::new(
+            RotorG312III::new('B', 'Q'),
+            RotorG312I::new('Z', 'P'),
+            RotorG312II::new('J', 'M'),
+            ReflectorG312::new('K').unwrap(),
+        );
+
+        assert_eq!(
+            machine.machine_settings().to_string(),
+            "UKW III-I-II 02-26-10 KQPM"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_set_positions() {
+        let mut machine = AbwehrEnigma::new(
+            RotorG312III::new('B', 'A'),
+            RotorG312I::new('Z', 'A'),
+            RotorG312II::new('J', 'A'),
+            ReflectorG312::new('A').unwrap(),
+        );
+
+        machine.set_positions(&['K', 'Q', 'P', 'M']).unwrap();
+        assert_eq!(
+            machine.encipher_str("ABWEHRGEHEIMSACHE").unwrap(),
+            "VGUDCJYMDNFTHYAEI"
+        );
+
+        machine.reset();
+        assert_eq!(machine.settings(), vec!['K', 'Q', 'P', 'M']);
+
+        assert_eq!(
+            machine.set_positions(&['k', 'Q', 'P', 'M']),
+            Err(EnigmaError::Reflector(ReflectorError::InvalidPosition('k')))
+        );
+        assert_eq!(
+            machine.set_positions(&['Q', 'P', 'M']),
+            Err(EnigmaError::RotorCount {
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(machine.settings(), vec!['K', 'Q', 'P', 'M']);
+    }
+}
@@ -0,0 +1,589 @@
+// src/commercial.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! The commercial Enigma D and K and their derivatives: the Swiss K, the
+//! Reichsbahn's rewired K, and the Enigma T built for Japan. None of these
+//! have a plugboard, and all let the reflector be set by hand. All but the T
+//! wire the entry wheel in keyboard order.
+
+use crate::entry::EntryWheel;
+use crate::permutation::Permutation;
+use crate::reflectors::{settable_reflector, SettableReflector};
+use crate::rotors::RotorEncode;
+use crate::stepping::{Pawl, Stepping};
+use crate::{_check_input, _set_positions, _set_ring_settings, Enigma, EnigmaError};
+
+#[cfg(feature = "alloc")]
+use crate::settings::{MachineSettings, RotorSettings};
+#[cfg(feature = "alloc")]
+use crate::snapshot::{ReflectorState, RotorState, Snapshot, SnapshotError};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use enigma_macros::RotorEncode;
+
+#[derive(RotorEncode)]
+#[name(I)]
+#[key_ordering(LPGSZMHAEOQKVXRFYBUTNICJDW)]
+#[notches(Y)]
+pub struct RotorKI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(II)]
+#[key_ordering(SLVGBTFXJQOHEWIRZYAMKPCNDU)]
+#[notches(E)]
+pub struct RotorKII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(III)]
+#[key_ordering(CJGDPSHKTURAWZXFMYNQOBVLIE)]
+#[notches(N)]
+pub struct RotorKIII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(I)]
+#[key_ordering(PEZUOHXSCVFMTBGLRINQJWAYDK)]
+#[notches(Y)]
+pub struct RotorSwissKI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(II)]
+#[key_ordering(ZOUESYDKFWPCIQXHMVBLGNJRAT)]
+#[notches(E)]
+pub struct RotorSwissKII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(III)]
+#[key_ordering(EHRVXGAOBQUSIMZFLYNWKTPDJC)]
+#[notches(N)]
+pub struct RotorSwissKIII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+/// The Enigma D was sold with the same wheels as the later K.
+pub type RotorDI = RotorKI;
+pub type RotorDII = RotorKII;
+pub type RotorDIII = RotorKIII;
+pub type ReflectorD = ReflectorK;
+
+settable_reflector!(
+    /// The settable reflector shared by the D, the K and the Swiss K.
+    ReflectorK,
+    b"IMETCGFRAYSQBZXWLHKDVUPOJN"
+);
+
+/// The Reichsbahn's K, with its wheels rewired.
+#[derive(RotorEncode)]
+#[name(I)]
+#[key_ordering(JGDQOXUSCAMIFRVTPNEWKBLZYH)]
+#[notches(N)]
+pub struct RotorRailwayI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(II)]
+#[key_ordering(NTZPSFBOKMWRCJDIVLAEYUXHGQ)]
+#[notches(E)]
+pub struct RotorRailwayII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(III)]
+#[key_ordering(JVIUBHTCDYAKEQZPOSGXNRMWFL)]
+#[notches(Y)]
+pub struct RotorRailwayIII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+settable_reflector!(ReflectorRailway, b"QYHOGNECVPUZTFDJAXWMKISRBL");
+
+/// The Enigma T (Tirpitz), with eight five-notch wheels.
+#[derive(RotorEncode)]
+#[name(I)]
+#[key_ordering(KPTYUELOCVGRFQDANJMBSWHZXI)]
+#[notches(WZEKQ)]
+pub struct RotorTI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(II)]
+#[key_ordering(UPHZLWEQMTDJXCAKSOIGVBYFNR)]
+#[notches(WZFLR)]
+pub struct RotorTII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(III)]
+#[key_ordering(QUDLYRFEKONVZAXWHMGPJBSICT)]
+#[notches(WZEKQ)]
+pub struct RotorTIII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(IV)]
+#[key_ordering(CIWTBKXNRESPFLYDAGVHQUOJZM)]
+#[notches(WZFLR)]
+pub struct RotorTIV {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(V)]
+#[key_ordering(UAXGISNJBVERDYLFZWTPCKOHMQ)]
+#[notches(YCFKR)]
+pub struct RotorTV {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(VI)]
+#[key_ordering(XFUZGALVHCNYSEWQTDMRBKPIOJ)]
+#[notches(XEIMQ)]
+pub struct RotorTVI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(VII)]
+#[key_ordering(BJVFTXPLNAYOZIKWGDQERUCHSM)]
+#[notches(YCFKR)]
+pub struct RotorTVII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[name(VIII)]
+#[key_ordering(YMTPNZHWKODAJXELUQVGCBISFR)]
+#[notches(XEIMQ)]
+pub struct RotorTVIII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+settable_reflector!(ReflectorT, b"GEKPBTAUMOCNILJDXZYFHWVQSR");
+
+pub struct CommercialEnigma<A, B, C, D, W> {
+    rotor1: A,
+    rotor2: B,
+    rotor3: C,
+    reflector: D,
+    entry: W,
+}
+
+impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: SettableReflector, W: EntryWheel>
+    CommercialEnigma<A, B, C, D, W>
+{
+    pub fn new(rotor1: A, rotor2: B, rotor3: C, reflector: D, entry: W) -> Self {
+        CommercialEnigma {
+            rotor1,
+            rotor2,
+            rotor3,
+            reflector,
+            entry,
+        }
+    }
+
+    pub fn reflector_position(&self) -> char {
+        self.reflector.position()
+    }
+
+    pub fn set_reflector_position(&mut self, position: char) -> Result<(), EnigmaError> {
+        Ok(self.reflector.set_position(position)?)
+    }
+}
+
+impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: SettableReflector, W: EntryWheel> Enigma
+    for CommercialEnigma<A, B, C, D, W>
+{
+    fn reset(&mut self) {
+        self.rotor1 = A::new(self.rotor1.ring_setting(), self.rotor1.init_position());
+        self.rotor2 = B::new(self.rotor2.ring_setting(), self.rotor2.init_position());
+        self.rotor3 = C::new(self.rotor3.ring_setting(), self.rotor3.init_position());
+    }
+
+    fn keypress(&mut self, input: char) -> Result<char, EnigmaError> {
+        _check_input(input)?;
+
+        Pawl.step(&mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3]);
+
+        let output = self.entry.transpose_in(input);
+        let output = self.rotor3.transpose_in(output);
+        let output = self.rotor2.transpose_in(output);
+        let output = self.rotor1.transpose_in(output);
+        let output = self.reflector.transpose(output);
+        let output = self.rotor1.transpose_out(output);
+        let output = self.rotor2.transpose_out(output);
+        let output = self.rotor3.transpose_out(output);
+        let output = self.entry.transpose_out(output);
+
+        Ok(output)
+    }
+
+    /// There is no plugboard on the commercial machines.
+    fn plugboard_transpose(&self, input: char) -> char {
+        input
+    }
+
+    #[cfg(feature = "alloc")]
+    fn settings(&self) -> Vec<char> {
+        vec![
+            self.rotor1.position(),
+            self.rotor2.position(),
+            self.rotor3.position(),
+        ]
+    }
+
+    fn set_ring_settings(&mut self, ring_settings: &[char]) -> Result<(), EnigmaError> {
+        _set_ring_settings(
+            &mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3],
+            ring_settings,
+        )
+    }
+
+    /// The reflector is set on its own, with
+    /// [`CommercialEnigma::set_reflector_position`].
+    fn set_positions(&mut self, positions: &[char]) -> Result<(), EnigmaError> {
+        _set_positions(
+            &mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3],
+            positions,
+        )
+    }
+
+    #[cfg(feature = "alloc")]
+    fn machine_settings(&self) -> MachineSettings {
+        MachineSettings::with_settable_reflector(
+            &self.reflector,
+            vec![
+                RotorSettings::of(&self.rotor1),
+                RotorSettings::of(&self.rotor2),
+                RotorSettings::of(&self.rotor3),
+            ],
+        )
+    }
+
+    fn permutation(&self) -> Permutation {
+        let entry = self.entry.permutation();
+        let rotors = self
+            .rotor3
+            .permutation()
+            .then(&self.rotor2.permutation())
+            .then(&self.rotor1.permutation());
+
+        entry
+            .then(&rotors)
+            .then(&self.reflector.permutation())
+            .then(&rotors.inverse())
+            .then(&entry.inverse())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rotors: vec![
+                RotorState::of(&self.rotor1),
+                RotorState::of(&self.rotor2),
+                RotorState::of(&self.rotor3),
+            ],
+            reflector: Some(ReflectorState {
+                init_position: self.reflector.position(),
+                position: self.reflector.position(),
+            }),
+            stecker: None,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), EnigmaError> {
+        snapshot.check(
+            &[self.rotor1.name(), self.rotor2.name(), self.rotor3.name()],
+            true,
+        )?;
+        if snapshot.stecker.is_some_and(|s| !s.is_identity()) {
+            return Err(SnapshotError::Stecker.into());
+        }
+
+        let reflector = snapshot.reflector.expect("checked above");
+        self.reflector.set_position(reflector.position)?;
+        snapshot.rotors[0].apply(&mut self.rotor1);
+        snapshot.rotors[1].apply(&mut self.rotor2);
+        snapshot.rotors[2].apply(&mut self.rotor3);
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+    use crate::entry::{QwertzEntry, TirpitzEntry};
+    use crate::reflectors::{Reflector, ReflectorError};
+    use alloc::string::String;
+
+    // The Enigma K, Swiss-K and D vectors below are regression vectors,
+    // worked out by a separate script from the same wiring tables rather
+    // than taken from a published example.
+    #[test]
+    fn test_enigma_k() {
+        let mut machine = CommercialEnigma::new(
+            RotorKI::new('A', 'A'),
+            RotorKII::new('A', 'A'),
+            RotorKIII::new('A', 'A'),
+            ReflectorK::default(),
+            QwertzEntry,
+        );
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "HWQIO");
+        assert_eq!(machine.settings(), vec!['A', 'A', 'F']);
+
+        machine.reset();
+        assert_eq!(machine.encipher_str("HWQIO").unwrap(), "AAAAA");
+    }
+
+    #[test]
+    fn test_enigma_k_settings() {
+        let mut machine = CommercialEnigma::new(
+            RotorKIII::new('C', 'X'),
+            RotorKI::new('Q', 'D'),
+            RotorKII::new('H', 'L'),
+            ReflectorK::new('M').unwrap(),
+            QwertzEntry,
+        );
+
+        assert_eq!(
+            machine.encipher_str("COMMERCIALENIGMA").unwrap(),
+            "JHAKMFXAGMGQPNVL"
+        );
+        assert_eq!(machine.settings(), vec!['X', 'D', 'B']);
+        assert_eq!(machine.reflector_position(), 'M');
+    }
+
+    #[test]
+    fn test_enigma_d_double_step() {
+        let mut machine = CommercialEnigma::new(
+            RotorDI::new('A', 'A'),
+            RotorDII::new('A', 'D'),
+            RotorDIII::new('A', 'M'),
+            ReflectorD::default(),
+            QwertzEntry,
+        );
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "NNXWN");
+        assert_eq!(machine.settings(), vec!['B', 'F', 'R']);
+    }
+
+    #[test]
+    fn test_swiss_k() {
+        let mut machine = CommercialEnigma::new(
+            RotorSwissKI::new('A', 'A'),
+            RotorSwissKII::new('A', 'A'),
+            RotorSwissKIII::new('A', 'A'),
+            ReflectorK::default(),
+            QwertzEntry,
+        );
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "YLYMI");
+
+        let mut machine = CommercialEnigma::new(
+            RotorSwissKII::new('F', 'R'),
+            RotorSwissKIII::new('U', 'M'),
+            RotorSwissKI::new('B', 'X'),
+            ReflectorK::new('J').unwrap(),
+            QwertzEntry,
+        );
+
+        assert_eq!(
+            machine.encipher_str("SWISSARMYTRAFFIC").unwrap(),
+            "JYDKCULBUCDLEWEX"
+        );
+        assert_eq!(machine.settings(), vec!['S', 'O', 'N']);
+    }
+
+    #[test]
+    fn test_reflector_position() {
+        let mut machine = CommercialEnigma::new(
+            RotorKI::new('A', 'A'),
+            RotorKII::new('A', 'A'),
+            RotorKIII::new('A', 'A'),
+            ReflectorK::default(),
+            QwertzEntry,
+        );
+
+        for position in 'A'..='Z' {
+            machine.set_reflector_position(position).unwrap();
+            let reflector = ReflectorK::new(position).unwrap();
+
+            assert!(reflector.permutation().is_involution());
+            assert!(reflector.permutation().fixed_points().is_empty());
+            assert!(machine.permutation().is_involution());
+        }
+
+        assert_eq!(
+            machine.set_reflector_position('a'),
+            Err(EnigmaError::Reflector(ReflectorError::InvalidPosition('a')))
+        );
+        assert_eq!(machine.reflector_position(), 'Z');
+    }
+
+    #[test]
+    fn test_permutation() {
+        let new = || {
+            CommercialEnigma::new(
+                RotorKIII::new('C', 'X'),
+                RotorKI::new('Q', 'D'),
+                RotorKII::new('H', 'L'),
+                ReflectorK::new('M').unwrap(),
+                QwertzEntry,
+            )
+        };
+
+        // Each letter goes through the substitution the machine makes once
+        // it has stepped.
+        let mut stepped = new();
+        let expected: String = "PERMUTATION"
+            .chars()
+            .map(|in_char| {
+                Pawl.step(&mut [
+                    &mut stepped.rotor1,
+                    &mut stepped.rotor2,
+                    &mut stepped.rotor3,
+                ]);
+                stepped.permutation().apply(in_char)
+            })
+            .collect();
+
+        assert_eq!(new().encipher_str("PERMUTATION").unwrap(), expected);
+    }
+
+    // Like those above, the Reichsbahn and Enigma T vectors are self-generated
+    // by a separate script from the same wiring tables, not taken from
+    // traffic.
+    #[test]
+    fn test_railway() {
+        let mut machine = CommercialEnigma::new(
+            RotorRailwayI::new('A', 'A'),
+            RotorRailwayII::new('A', 'A'),
+            RotorRailwayIII::new('A', 'A'),
+            ReflectorRailway::default(),
+            QwertzEntry,
+        );
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "ZBIJB");
+
+        let mut machine = CommercialEnigma::new(
+            RotorRailwayIII::new('M', 'J'),
+            RotorRailwayII::new('E', 'D'),
+            RotorRailwayI::new('X', 'M'),
+            ReflectorRailway::new('S').unwrap(),
+            QwertzEntry,
+        );
+
+        assert_eq!(
+            machine.encipher_str("REICHSBAHNDIREKTION").unwrap(),
+            "MWOHWZUNRVLMLHOVWQJ"
+        );
+        assert_eq!(machine.settings(), vec!['K', 'F', 'F']);
+    }
+
+    #[test]
+    fn test_tirpitz() {
+        let mut machine = CommercialEnigma::new(
+            RotorTI::new('A', 'A'),
+            RotorTII::new('A', 'A'),
+            RotorTIII::new('A', 'A'),
+            ReflectorT::default(),
+            TirpitzEntry,
+        );
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "FWRYL");
+
+        let mut machine = CommercialEnigma::new(
+            RotorTVIII::new('Q', 'V'),
+            RotorTV::new('D', 'B'),
+            RotorTVI::new('K', 'W'),
+            ReflectorT::new('H').unwrap(),
+            TirpitzEntry,
+        );
+
+        assert_eq!(
+            machine.encipher_str("TIRPITZKYOTOTOKIO").unwrap(),
+            "GNYYHDVWTJMVMILCJ"
+        );
+        assert_eq!(machine.settings(), vec!['X', 'H', 'N']);
+
+        machine.reset();
+        assert_eq!(
+            machine.encipher_str("GNYYHDVWTJMVMILCJ").unwrap(),
+            "TIRPITZKYOTOTOKIO"
+        );
+    }
+}
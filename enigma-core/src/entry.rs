@@ -0,0 +1,127 @@
+// src/entry.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::permutation::Permutation;
+
+/// The keyboard in the order the commercial machines wired it to the entry
+/// wheel: `Q` goes to contact `A`, `W` to contact `B`, and so on.
+const QWERTZ: &[u8; 26] = b"QWERTZUIOASDFGHJKPYXCVBNML";
+
+/// The Enigma T's entry wheel, which follows neither the keyboard nor the
+/// alphabet.
+const TIRPITZ: &[u8; 26] = b"KZROUQHYAIGBLWVSTDXFPNMCJE";
+
+fn _key_to_contact(keys: &[u8; 26], input: char) -> char {
+    match keys.iter().position(|key| *key as char == input) {
+        Some(contact) => (b'A' + contact as u8) as char,
+        None => input,
+    }
+}
+
+fn _contact_to_key(keys: &[u8; 26], input: char) -> char {
+    match input {
+        'A'..='Z' => keys[(input as u8 - b'A') as usize] as char,
+        _ => input,
+    }
+}
+
+/// The fixed wheel (Eintrittswalze) between the keyboard and the rotors.
+pub trait EntryWheel {
+    /// From a key to the contact it reaches on the rightmost rotor.
+    fn transpose_in(&self, input: char) -> char;
+    /// From a rotor contact back out to its lamp.
+    fn transpose_out(&self, input: char) -> char;
+
+    fn permutation(&self) -> Permutation {
+        Permutation::from_fn(|c| self.transpose_in(c)).expect("entry wiring is a permutation")
+    }
+}
+
+/// The military entry wheel, which wires every key straight through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlphabeticalEntry;
+
+impl EntryWheel for AlphabeticalEntry {
+    fn transpose_in(&self, input: char) -> char {
+        input
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        input
+    }
+}
+
+/// The commercial entry wheel, wired in keyboard order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QwertzEntry;
+
+impl EntryWheel for QwertzEntry {
+    fn transpose_in(&self, input: char) -> char {
+        _key_to_contact(QWERTZ, input)
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        _contact_to_key(QWERTZ, input)
+    }
+}
+
+/// The entry wheel of the Enigma T, built for Japan.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TirpitzEntry;
+
+impl EntryWheel for TirpitzEntry {
+    fn transpose_in(&self, input: char) -> char {
+        _key_to_contact(TIRPITZ, input)
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        _contact_to_key(TIRPITZ, input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alphabetical() {
+        assert!(AlphabeticalEntry.permutation().is_identity());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_qwertz() {
+        let entry = QwertzEntry;
+
+        assert_eq!(entry.transpose_in('Q'), 'A');
+        assert_eq!(entry.transpose_in('L'), 'Z');
+        assert_eq!(entry.transpose_out('B'), 'W');
+        assert_eq!(
+            entry.permutation().inverse().wiring(),
+            "QWERTZUIOASDFGHJKPYXCVBNML"
+        );
+
+        for c in 'A'..='Z' {
+            assert_eq!(entry.transpose_out(entry.transpose_in(c)), c);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tirpitz() {
+        let entry = TirpitzEntry;
+
+        assert_eq!(entry.transpose_in('K'), 'A');
+        assert_eq!(entry.transpose_out('Z'), 'E');
+        assert_eq!(
+            entry.permutation().inverse().wiring(),
+            "KZROUQHYAIGBLWVSTDXFPNMCJE"
+        );
+    }
+}
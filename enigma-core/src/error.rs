@@ -0,0 +1,194 @@
+// src/error.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::plugboard::PlugboardError;
+use crate::reflectors::ReflectorError;
+use crate::rotors::RotorError;
+use crate::snapshot::SnapshotError;
+use crate::uhr::UhrError;
+
+use core::error::Error;
+use core::fmt;
+
+/// A part of the machine, used to say where an error came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Component {
+    Plugboard,
+    /// A rotor slot, counted from the left starting at 1.
+    Rotor(usize),
+    Reflector,
+    Uhr,
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Component::Plugboard => write!(f, "plugboard"),
+            Component::Rotor(slot) => write!(f, "rotor in slot {slot}"),
+            Component::Reflector => write!(f, "reflector"),
+            Component::Uhr => write!(f, "Uhr"),
+        }
+    }
+}
+
+/// Every way the crate can fail. The per-component errors are kept intact as
+/// the [`Error::source`] of the variant that wraps them.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum EnigmaError {
+    NonAsciiCharacter(char),
+    NonAlphabeticCharacter(char),
+    NonUppercaseCharacter(char),
+    Plugboard(PlugboardError),
+    Uhr(UhrError),
+    Reflector(ReflectorError),
+    Snapshot(SnapshotError),
+    Rotor {
+        slot: usize,
+        source: RotorError,
+    },
+    /// A setting was given for the wrong number of rotors.
+    RotorCount {
+        expected: usize,
+        found: usize,
+    },
+    /// There is no rotor slot here, counted from the left from 1 like
+    /// [`Component::Rotor`].
+    NoSuchSlot(usize),
+}
+
+impl EnigmaError {
+    /// The component the error came from, if it is specific to one.
+    pub fn component(&self) -> Option<Component> {
+        match self {
+            EnigmaError::Plugboard(_) => Some(Component::Plugboard),
+            EnigmaError::Uhr(_) => Some(Component::Uhr),
+            EnigmaError::Reflector(_) => Some(Component::Reflector),
+            EnigmaError::Rotor { slot, .. } => Some(Component::Rotor(*slot)),
+            _ => None,
+        }
+    }
+}
+
+impl Error for EnigmaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EnigmaError::Plugboard(source) => Some(source),
+            EnigmaError::Uhr(source) => Some(source),
+            EnigmaError::Reflector(source) => Some(source),
+            EnigmaError::Snapshot(source) => Some(source),
+            EnigmaError::Rotor { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EnigmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnigmaError::NonAsciiCharacter(c) => write!(f, "'{c}' is not an ASCII character"),
+            EnigmaError::NonAlphabeticCharacter(c) => {
+                write!(f, "'{c}' is not an alphabetic character")
+            }
+            EnigmaError::NonUppercaseCharacter(c) => {
+                write!(f, "'{c}' is not an uppercase character")
+            }
+            EnigmaError::Plugboard(_) => write!(f, "invalid plugboard configuration"),
+            EnigmaError::Uhr(_) => write!(f, "invalid Uhr configuration"),
+            EnigmaError::Reflector(_) => write!(f, "invalid reflector setting"),
+            EnigmaError::Snapshot(_) => write!(f, "snapshot does not fit this machine"),
+            EnigmaError::Rotor { slot, .. } => {
+                write!(f, "invalid setting for {}", Component::Rotor(*slot))
+            }
+            EnigmaError::RotorCount { expected, found } => {
+                write!(f, "expected settings for {expected} rotors, found {found}")
+            }
+            EnigmaError::NoSuchSlot(slot) => write!(f, "there is no rotor slot {slot}"),
+        }
+    }
+}
+
+impl From<PlugboardError> for EnigmaError {
+    fn from(source: PlugboardError) -> Self {
+        EnigmaError::Plugboard(source)
+    }
+}
+
+impl From<UhrError> for EnigmaError {
+    fn from(source: UhrError) -> Self {
+        EnigmaError::Uhr(source)
+    }
+}
+
+impl From<ReflectorError> for EnigmaError {
+    fn from(source: ReflectorError) -> Self {
+        EnigmaError::Reflector(source)
+    }
+}
+
+impl From<SnapshotError> for EnigmaError {
+    fn from(source: SnapshotError) -> Self {
+        EnigmaError::Snapshot(source)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn test_source_chaining() {
+        let err: EnigmaError = PlugboardError::CharacterAlreadyWired('A').into();
+
+        assert_eq!(err.component(), Some(Component::Plugboard));
+        assert_eq!(format!("{err}"), "invalid plugboard configuration");
+        assert_eq!(
+            format!("{}", err.source().unwrap()),
+            "'A' already wired to the board"
+        );
+
+        let err = EnigmaError::Rotor {
+            slot: 2,
+            source: RotorError::InvalidPosition('9'),
+        };
+
+        assert_eq!(err.component(), Some(Component::Rotor(2)));
+        assert_eq!(format!("{err}"), "invalid setting for rotor in slot 2");
+        assert_eq!(
+            format!("{}", err.source().unwrap()),
+            "'9' is not a valid rotor position, must be A-Z"
+        );
+    }
+
+    #[test]
+    fn test_input_errors() {
+        let err = EnigmaError::NonUppercaseCharacter('e');
+
+        assert_eq!(err.component(), None);
+        assert!(err.source().is_none());
+        assert_eq!(format!("{err}"), "'e' is not an uppercase character");
+    }
+
+    #[test]
+    fn test_slot_errors() {
+        let err = EnigmaError::RotorCount {
+            expected: 3,
+            found: 4,
+        };
+
+        assert_eq!(err.component(), None);
+        assert_eq!(format!("{err}"), "expected settings for 3 rotors, found 4");
+        assert_eq!(
+            format!("{}", EnigmaError::NoSuchSlot(5)),
+            "there is no rotor slot 5"
+        );
+    }
+}
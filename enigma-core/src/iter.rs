@@ -0,0 +1,160 @@
+// src/iter.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::{Enigma, EnigmaError, NonLetters};
+
+/// Types each character of an iterator on a machine, in place of the usual
+/// `chars().map(|c| machine.keypress(c))` loop:
+///
+/// ```ignore
+/// let ciphertext: String = "attack at dawn".chars().encipher(&mut machine).collect::<Result<_, _>>()?;
+/// ```
+pub trait EncipherExt: Iterator<Item = char> + Sized {
+    fn encipher<M: Enigma + ?Sized>(self, machine: &mut M) -> Encipher<'_, Self, M> {
+        Encipher {
+            chars: self,
+            machine,
+            non_letters: NonLetters::default(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> EncipherExt for I {}
+
+/// The iterator returned by [`EncipherExt::encipher`]. Lowercase letters are
+/// typed as uppercase, and anything else is handled as its [`NonLetters`]
+/// policy says.
+pub struct Encipher<'a, I, M: ?Sized> {
+    chars: I,
+    machine: &'a mut M,
+    non_letters: NonLetters,
+}
+
+impl<I, M: ?Sized> Encipher<'_, I, M> {
+    /// What to do with characters that are not letters; passed through by
+    /// default.
+    pub fn non_letters(mut self, non_letters: NonLetters) -> Self {
+        self.non_letters = non_letters;
+        self
+    }
+}
+
+impl<I: Iterator<Item = char>, M: Enigma + ?Sized> Iterator for Encipher<'_, I, M> {
+    type Item = Result<char, EnigmaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let input = self.chars.next()?;
+
+            if input.is_ascii_alphabetic() {
+                return Some(self.machine.keypress(input.to_ascii_uppercase()));
+            }
+
+            match self.non_letters {
+                NonLetters::PassThrough => return Some(Ok(input)),
+                NonLetters::Drop => continue,
+                NonLetters::Error if input.is_ascii() => {
+                    return Some(Err(EnigmaError::NonAlphabeticCharacter(input)))
+                }
+                NonLetters::Error => return Some(Err(EnigmaError::NonAsciiCharacter(input))),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.non_letters {
+            NonLetters::Drop => (0, self.chars.size_hint().1),
+            _ => self.chars.size_hint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reflectors::ReflectorB;
+    use crate::rotors::{RotorEncode, RotorI, RotorII, RotorIII};
+    use crate::{plugboard, ArmyEnigma};
+
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+
+    fn machine() -> ArmyEnigma<RotorI, RotorII, RotorIII, ReflectorB, plugboard::Plugboard> {
+        ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_encipher() {
+        let mut machine = machine();
+        let mut output = "aA-a".chars().encipher(&mut machine);
+
+        assert_eq!(output.next(), Some(Ok('B')));
+        assert_eq!(output.next(), Some(Ok('D')));
+        assert_eq!(output.next(), Some(Ok('-')));
+        assert_eq!(output.next(), Some(Ok('Z')));
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    fn test_non_letters() {
+        let mut machine = machine();
+        let mut output = "A A\u{e4}1"
+            .chars()
+            .encipher(&mut machine)
+            .non_letters(NonLetters::Error);
+
+        assert_eq!(output.next(), Some(Ok('B')));
+        assert_eq!(
+            output.next(),
+            Some(Err(EnigmaError::NonAlphabeticCharacter(' ')))
+        );
+        assert_eq!(output.next(), Some(Ok('D')));
+        assert_eq!(
+            output.next(),
+            Some(Err(EnigmaError::NonAsciiCharacter('\u{e4}')))
+        );
+
+        let mut machine = self::machine();
+        let count = "A, A. A! A? A"
+            .chars()
+            .encipher(&mut machine)
+            .non_letters(NonLetters::Drop)
+            .filter(|c| c.is_ok())
+            .count();
+
+        assert_eq!(count, 5);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_collect() {
+        let mut machine = machine();
+        let output: Result<String, _> = "AAAAA".chars().encipher(&mut machine).collect();
+        assert_eq!(output, Ok("BDZGO".into()));
+
+        machine.reset();
+        assert_eq!(machine.encipher_str("bdzgo"), Ok("AAAAA".into()));
+
+        machine.reset();
+        assert_eq!(machine.encipher_str("AAA AA"), Ok("BDZ GO".into()));
+
+        let output: Result<String, _> = "A\u{e4}"
+            .chars()
+            .encipher(&mut machine)
+            .non_letters(NonLetters::Error)
+            .collect();
+        assert_eq!(output, Err(EnigmaError::NonAsciiCharacter('\u{e4}')));
+    }
+}
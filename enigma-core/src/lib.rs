@@ -7,35 +7,61 @@
 // file may not be copied, modified, or distributed except according to those
 // terms.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate self as enigma_core;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod abwehr;
+pub mod commercial;
+pub mod entry;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod io;
+pub mod iter;
+#[cfg(feature = "alloc")]
+pub mod machine;
+#[cfg(feature = "alloc")]
+pub mod message;
+pub mod model;
+#[cfg(feature = "alloc")]
+pub mod period;
+pub mod permutation;
 pub mod plugboard;
+#[cfg(feature = "alloc")]
+pub mod procedure;
 pub mod reflectors;
+#[cfg(feature = "alloc")]
+pub mod registry;
 pub mod rotors;
-
+#[cfg(feature = "alloc")]
+pub mod settings;
+pub mod snapshot;
+pub mod stepping;
+#[cfg(feature = "alloc")]
+pub mod typex;
+pub mod uhr;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+#[cfg(feature = "alloc")]
+use iter::EncipherExt;
+#[cfg(feature = "alloc")]
+use period::StepCycle;
+use permutation::Permutation;
+use plugboard::Stecker;
 use reflectors::Reflector;
-use rotors::RotorEncode;
-
-use std::error::Error;
-use std::fmt;
-
-#[derive(Debug, PartialEq)]
-pub enum EnigmaError {
-    NonAsciiCharacter(char),
-    NonAlphabeticCharacter(char),
-    NonUppercaseCharacter(char),
-}
+use rotors::{RotorEncode, RotorError};
+#[cfg(feature = "alloc")]
+use settings::{MachineSettings, RotorSettings};
+#[cfg(feature = "alloc")]
+use snapshot::{RotorState, Snapshot};
+use stepping::{Pawl, Stepping};
 
-impl Error for EnigmaError {}
-impl fmt::Display for EnigmaError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (character, error_text) = match self {
-            EnigmaError::NonAsciiCharacter(c) => (c, "ASCII"),
-            EnigmaError::NonAlphabeticCharacter(c) => (c, "alphabetic"),
-            EnigmaError::NonUppercaseCharacter(c) => (c, "uppercase"),
-        };
-
-        write!(f, "'{character}' is not an {error_text} character")
-    }
-}
+pub use enigma_macros::{plugboard, reflector, rotor};
+pub use error::{Component, EnigmaError};
 
 fn _check_input(input: char) -> Result<char, EnigmaError> {
     match input {
@@ -46,11 +72,106 @@ fn _check_input(input: char) -> Result<char, EnigmaError> {
     }
 }
 
+/// What to do with input that is not a letter, which the machine has no key
+/// for. Lowercase letters are always typed as uppercase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonLetters {
+    /// Leave it in the output as it is.
+    #[default]
+    PassThrough,
+    /// Leave it out of the output.
+    Drop,
+    /// Stop with an [`EnigmaError`].
+    Error,
+}
+
+/// Check one setting per rotor, each `A`-`Z`, before any rotor is touched.
+fn _check_rotor_settings(
+    settings: &[char],
+    rotors: usize,
+    invalid: fn(char) -> RotorError,
+) -> Result<(), EnigmaError> {
+    if settings.len() != rotors {
+        return Err(EnigmaError::RotorCount {
+            expected: rotors,
+            found: settings.len(),
+        });
+    }
+
+    match settings.iter().position(|c| !c.is_ascii_uppercase()) {
+        Some(n) => Err(EnigmaError::Rotor {
+            slot: n + 1,
+            source: invalid(settings[n]),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn _set_ring_settings(
+    rotors: &mut [&mut dyn RotorEncode],
+    ring_settings: &[char],
+) -> Result<(), EnigmaError> {
+    _check_rotor_settings(ring_settings, rotors.len(), RotorError::InvalidRingSetting)?;
+
+    for (rotor, ring_setting) in rotors.iter_mut().zip(ring_settings) {
+        rotor.set_ring_setting(*ring_setting);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn _set_positions(
+    rotors: &mut [&mut dyn RotorEncode],
+    positions: &[char],
+) -> Result<(), EnigmaError> {
+    _check_rotor_settings(positions, rotors.len(), RotorError::InvalidPosition)?;
+
+    for (rotor, position) in rotors.iter_mut().zip(positions) {
+        rotor.set_init_position(*position);
+        rotor.set_position(*position);
+    }
+
+    Ok(())
+}
+
 pub trait Enigma {
     fn reset(&mut self);
     fn keypress(&mut self, input: char) -> Result<char, EnigmaError>;
+    /// Type a whole message, with lowercase letters typed as uppercase and
+    /// anything else passed through. See [`EncipherExt`] for other
+    /// policies.
+    #[cfg(feature = "alloc")]
+    fn encipher_str(&mut self, input: &str) -> Result<String, EnigmaError> {
+        input.chars().encipher(self).collect()
+    }
+    /// The stecker substitution on the way in to the rotors.
     fn plugboard_transpose(&self, input: char) -> char;
+    #[cfg(feature = "alloc")]
     fn settings(&self) -> Vec<char>;
+    /// Turn every rotor's ring, from left to right, leaving the window
+    /// letters where they are. Nothing changes if any setting is invalid.
+    fn set_ring_settings(&mut self, ring_settings: &[char]) -> Result<(), EnigmaError>;
+    /// Set the machine to new starting positions, given in the same order as
+    /// [`Enigma::settings`], and turn it to them; [`Enigma::reset`] comes
+    /// back here. Nothing changes if any position is invalid.
+    fn set_positions(&mut self, positions: &[char]) -> Result<(), EnigmaError>;
+    /// The whole key as it stands: reflector, wheel order, ring settings,
+    /// window letters and stecker, with which rotors sit at a notch.
+    #[cfg(feature = "alloc")]
+    fn machine_settings(&self) -> MachineSettings;
+
+    /// The substitution the machine performs at its current rotor positions,
+    /// before stepping for the next keypress.
+    fn permutation(&self) -> Permutation;
+
+    /// Capture the machine's whole state, to come back to with
+    /// [`Enigma::restore`].
+    #[cfg(feature = "alloc")]
+    fn snapshot(&self) -> Snapshot;
+    /// Put the machine back into a captured state. Nothing changes if the
+    /// snapshot does not fit the machine.
+    #[cfg(feature = "alloc")]
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), EnigmaError>;
 }
 
 pub struct ArmyEnigma<A, B, C, D, E> {
@@ -69,20 +190,68 @@ impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector>
         rotor2: B,
         rotor3: C,
         reflector: D,
-        plugboard: Option<plugboard::Plugboard>,
+        plugboard: impl Into<Option<plugboard::Plugboard>>,
     ) -> Self {
         ArmyEnigma {
             rotor1,
             rotor2,
             rotor3,
             reflector,
-            plugboard,
+            plugboard: plugboard.into(),
         }
     }
+
+    /// Replace every cable on the plugboard. Nothing changes if the pairs
+    /// are not a valid wiring.
+    pub fn set_plugboard(&mut self, pairs: &[(char, char)]) -> Result<(), EnigmaError> {
+        self.plugboard = Some(plugboard::Plugboard::new(pairs)?);
+
+        Ok(())
+    }
 }
 
-impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Enigma
-    for ArmyEnigma<A, B, C, D, plugboard::Plugboard>
+impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector, E: Stecker>
+    ArmyEnigma<A, B, C, D, E>
+{
+    /// Build a machine around any stecker, such as an [`uhr::Uhr`].
+    pub fn with_stecker(rotor1: A, rotor2: B, rotor3: C, reflector: D, stecker: E) -> Self {
+        ArmyEnigma {
+            rotor1,
+            rotor2,
+            rotor3,
+            reflector,
+            plugboard: Some(stecker),
+        }
+    }
+
+    /// The stecker, to be rewired in place, e.g. with
+    /// [`plugboard::Plugboard::add_pair`].
+    pub fn stecker_mut(&mut self) -> Option<&mut E> {
+        self.plugboard.as_mut()
+    }
+
+    /// Swap in a different stecker, or take it out altogether.
+    pub fn set_stecker(&mut self, stecker: impl Into<Option<E>>) {
+        self.plugboard = stecker.into();
+    }
+
+    /// Trace the rotor positions from the current setting until they repeat,
+    /// without disturbing the machine itself.
+    #[cfg(feature = "alloc")]
+    pub fn step_cycle(&self) -> StepCycle {
+        let mut rotor1 = A::new(self.rotor1.ring_setting(), self.rotor1.position());
+        let mut rotor2 = B::new(self.rotor2.ring_setting(), self.rotor2.position());
+        let mut rotor3 = C::new(self.rotor3.ring_setting(), self.rotor3.position());
+
+        period::trace_cycle(self.settings(), || {
+            Pawl.step(&mut [&mut rotor1, &mut rotor2, &mut rotor3]);
+            vec![rotor1.position(), rotor2.position(), rotor3.position()]
+        })
+    }
+}
+
+impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector, E: Stecker> Enigma
+    for ArmyEnigma<A, B, C, D, E>
 {
     fn reset(&mut self) {
         self.rotor1 = A::new(self.rotor1.ring_setting(), self.rotor1.init_position());
@@ -93,19 +262,7 @@ impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Enigma
     fn keypress(&mut self, input: char) -> Result<char, EnigmaError> {
         _check_input(input)?;
 
-        let right_at_notch = self.rotor3.at_notch();
-        let middle_at_notch = self.rotor2.at_notch();
-
-        self.rotor3.advance();
-
-        if right_at_notch {
-            self.rotor2.advance();
-        }
-
-        if middle_at_notch {
-            self.rotor2.advance();
-            self.rotor1.advance();
-        }
+        Pawl.step(&mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3]);
 
         let output = self.plugboard_transpose(input);
         let output = self.rotor3.transpose_in(output);
@@ -115,18 +272,22 @@ impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Enigma
         let output = self.rotor1.transpose_out(output);
         let output = self.rotor2.transpose_out(output);
         let output = self.rotor3.transpose_out(output);
-        let output = self.plugboard_transpose(output);
+        let output = match self.plugboard {
+            Some(ref pb) => pb.transpose_out(output),
+            None => output,
+        };
 
         Ok(output)
     }
 
     fn plugboard_transpose(&self, input: char) -> char {
         match self.plugboard {
-            Some(ref pb) => pb.transpose(input),
+            Some(ref pb) => pb.transpose_in(input),
             None => input,
         }
     }
 
+    #[cfg(feature = "alloc")]
     fn settings(&self) -> Vec<char> {
         vec![
             self.rotor1.position(),
@@ -134,16 +295,90 @@ impl<A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Enigma
             self.rotor3.position(),
         ]
     }
+
+    fn set_ring_settings(&mut self, ring_settings: &[char]) -> Result<(), EnigmaError> {
+        _set_ring_settings(
+            &mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3],
+            ring_settings,
+        )
+    }
+
+    fn set_positions(&mut self, positions: &[char]) -> Result<(), EnigmaError> {
+        _set_positions(
+            &mut [&mut self.rotor1, &mut self.rotor2, &mut self.rotor3],
+            positions,
+        )
+    }
+
+    #[cfg(feature = "alloc")]
+    fn machine_settings(&self) -> MachineSettings {
+        MachineSettings::with_reflector(
+            &self.reflector,
+            vec![
+                RotorSettings::of(&self.rotor1),
+                RotorSettings::of(&self.rotor2),
+                RotorSettings::of(&self.rotor3),
+            ],
+            snapshot::_snapshot_stecker(&self.plugboard),
+        )
+    }
+
+    fn permutation(&self) -> Permutation {
+        let plugboard = match self.plugboard {
+            Some(ref pb) => pb.permutation(),
+            None => Permutation::identity(),
+        };
+        let rotors = self
+            .rotor3
+            .permutation()
+            .then(&self.rotor2.permutation())
+            .then(&self.rotor1.permutation());
+
+        plugboard
+            .then(&rotors)
+            .then(&self.reflector.permutation())
+            .then(&rotors.inverse())
+            .then(&plugboard.inverse())
+    }
+
+    #[cfg(feature = "alloc")]
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rotors: vec![
+                RotorState::of(&self.rotor1),
+                RotorState::of(&self.rotor2),
+                RotorState::of(&self.rotor3),
+            ],
+            reflector: None,
+            stecker: snapshot::_snapshot_stecker(&self.plugboard),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), EnigmaError> {
+        snapshot.check(
+            &[self.rotor1.name(), self.rotor2.name(), self.rotor3.name()],
+            false,
+        )?;
+        snapshot::_restore_stecker(&mut self.plugboard, snapshot.stecker.as_ref())?;
+
+        snapshot.rotors[0].apply(&mut self.rotor1);
+        snapshot.rotors[1].apply(&mut self.rotor2);
+        snapshot.rotors[2].apply(&mut self.rotor3);
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::plugboard::*;
     use crate::reflectors::*;
     use crate::rotors::*;
-    use std::collections::HashMap;
+    #[cfg(feature = "alloc")]
+    use alloc::string::{String, ToString};
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_simple() {
         let mut machine = ArmyEnigma::new(
@@ -156,10 +391,7 @@ mod test {
 
         let input: String = "AAAAA".into();
         let expected: String = "BDZGO".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -167,6 +399,27 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[test]
+    fn test_keypress() {
+        // The same as `test_simple`, one key at a time, which is all a
+        // machine without `alloc` offers.
+        let mut machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB {},
+            plugboard! {},
+        );
+
+        for (input, expected) in "AAAAA".chars().zip("BDZGO".chars()) {
+            assert_eq!(machine.keypress(input), Ok(expected));
+        }
+
+        machine.reset();
+        assert_eq!(machine.keypress('A'), Ok('B'));
+    }
+
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_enigma() {
         let mut machine = ArmyEnigma::new(
@@ -179,10 +432,7 @@ mod test {
 
         let input: String = "ENIGMA".into();
         let expected: String = "FQGAHW".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -190,6 +440,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_homogenous_rotors() {
         let mut machine = ArmyEnigma::new(
@@ -202,10 +453,7 @@ mod test {
 
         let input: String = "AAA".into();
         let expected: String = "UOT".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -213,6 +461,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_turnover() {
         let mut machine = ArmyEnigma::new(
@@ -225,10 +474,7 @@ mod test {
 
         let input: String = "AAAAA".into();
         let expected: String = "EQIBM".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -236,6 +482,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_gap_fog() {
         let mut machine = ArmyEnigma::new(
@@ -248,10 +495,7 @@ mod test {
 
         let input: String = "ADVANCEMINSK".into();
         let expected: String = "PXBUYVUGEGCI".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -259,6 +503,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_bbb_fog() {
         let mut machine = ArmyEnigma::new(
@@ -271,10 +516,7 @@ mod test {
 
         let input: String = "ADVANCEMINSK".into();
         let expected: String = "YXLEOPVFDTOY".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -282,6 +524,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_plugboard_input() {
         let mut machine = ArmyEnigma::new(
@@ -298,10 +541,7 @@ mod test {
 
         let input: String = "FOG".into();
         let expected: String = "AAA".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -309,6 +549,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_plugboard_output() {
         let mut machine = ArmyEnigma::new(
@@ -325,10 +566,7 @@ mod test {
 
         let input: String = "AAA".into();
         let expected: String = "FOG".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -336,6 +574,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_ring_settings() {
         let mut machine = ArmyEnigma::new(
@@ -348,10 +587,7 @@ mod test {
 
         let input: String = "AAA".into();
         let expected: String = "TBU".into();
-        let output: String = input
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
@@ -359,6 +595,7 @@ mod test {
         assert_eq!(expected_settings, machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_mirrors_correctly() {
         let mut machine = ArmyEnigma::new(
@@ -370,11 +607,7 @@ mod test {
         );
 
         let initial: String = "ADVANCEMINSK".into();
-        let encoded: String = initial
-            .clone()
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let encoded: String = machine.encipher_str(&initial).unwrap();
 
         let mut machine = ArmyEnigma::new(
             RotorIV::new('L', 'F'),
@@ -384,10 +617,7 @@ mod test {
             plugboard! {},
         );
 
-        let decoded: String = encoded
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let decoded: String = machine.encipher_str(&encoded).unwrap();
 
         assert_eq!(initial, decoded);
     }
@@ -419,9 +649,190 @@ mod test {
             Err(EnigmaError::NonUppercaseCharacter('e'))
         );
 
+        #[cfg(feature = "alloc")]
         assert_eq!(vec!['F', 'I', 'B'], machine.settings());
     }
 
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_permutation() {
+        let mut machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'B'),
+            ReflectorB {},
+            plugboard! {},
+        );
+
+        let perm = machine.permutation();
+
+        assert!(perm.is_involution());
+        assert!(perm.fixed_points().is_empty());
+        assert_eq!(perm.cycle_type(), vec![2; 13]);
+        assert_eq!(perm.apply('A'), 'B');
+
+        machine.keypress('A').unwrap();
+        let perm = machine.permutation();
+        assert_eq!(perm.apply('A'), 'D');
+        assert_eq!(vec!['A', 'A', 'C'], machine.settings());
+    }
+
+    #[test]
+    fn test_permutation_plugboard() {
+        let machine = ArmyEnigma::new(
+            RotorI::new('B', 'A'),
+            RotorI::new('B', 'A'),
+            RotorI::new('B', 'B'),
+            ReflectorB {},
+            plugboard! {
+                'F' => 'T',
+                'O' => 'B',
+                'G' => 'U'
+            },
+        );
+
+        assert_eq!(machine.permutation().apply('F'), 'A');
+        assert_eq!(machine.permutation().apply('A'), 'F');
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_step_cycle() {
+        let machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB {},
+            plugboard! {},
+        );
+
+        let cycle = machine.step_cycle();
+
+        // The double-step skips one middle position per left-rotor turn.
+        assert!(cycle.lead_in.is_empty());
+        assert_eq!(cycle.period(), 26 * 25 * 26);
+        assert_eq!(cycle.states[0], vec!['A', 'A', 'A']);
+        assert_eq!(vec!['A', 'A', 'A'], machine.settings());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_step_cycle_double_step() {
+        let machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'D'),
+            RotorIII::new('A', 'U'),
+            ReflectorB {},
+            plugboard! {},
+        );
+
+        let cycle = machine.step_cycle();
+
+        assert_eq!(cycle.states[0], vec!['A', 'D', 'U']);
+        assert_eq!(cycle.states[1], vec!['A', 'D', 'V']);
+        assert_eq!(cycle.states[2], vec!['A', 'E', 'W']);
+        assert_eq!(cycle.states[3], vec!['B', 'F', 'X']);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_step_cycle_lead_in() {
+        let machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'E'),
+            RotorIII::new('A', 'A'),
+            ReflectorB {},
+            plugboard! {},
+        );
+
+        let cycle = machine.step_cycle();
+
+        assert_eq!(cycle.lead_in, vec![vec!['A', 'E', 'A']]);
+        assert_eq!(cycle.states[0], vec!['B', 'F', 'B']);
+        assert_eq!(cycle.period(), 26 * 25 * 26);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_step_cycle_multiple_notches() {
+        let machine = ArmyEnigma::new(
+            RotorVI::new('A', 'A'),
+            RotorVII::new('A', 'A'),
+            RotorVIII::new('A', 'A'),
+            ReflectorB {},
+            plugboard! {},
+        );
+
+        let cycle = machine.step_cycle();
+
+        // Every rotor turns over twice per revolution, so the middle rotor
+        // steps every 13 keypresses and visits only 12 positions per turn of
+        // the left rotor. A right rotor at A can only be reached by turning
+        // over from Z, which would have stepped the middle rotor too.
+        assert_eq!(cycle.lead_in, vec![vec!['A', 'A', 'A']]);
+        assert_eq!(cycle.states[12], vec!['A', 'B', 'N']);
+        assert_eq!(cycle.period(), 13 * 12 * 26);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_uhr() {
+        let cables = [
+            ('A', 'V'),
+            ('B', 'S'),
+            ('C', 'G'),
+            ('D', 'L'),
+            ('F', 'U'),
+            ('H', 'Z'),
+            ('I', 'N'),
+            ('K', 'M'),
+            ('O', 'W'),
+            ('R', 'X'),
+        ];
+
+        let mut machine = ArmyEnigma::with_stecker(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB {},
+            uhr::Uhr::new(&cables, 0).unwrap(),
+        );
+        let mut plugged = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB {},
+            plugboard!("AV BS CG DL FU HZ IN KM OW RX"),
+        );
+
+        // At 00 the Uhr is indistinguishable from plain stecker cables.
+        for in_char in "ADVANCEMINSK".chars() {
+            assert_eq!(machine.keypress(in_char), plugged.keypress(in_char));
+        }
+
+        for position in [1, 13, 27, 39] {
+            let mut machine = ArmyEnigma::with_stecker(
+                RotorI::new('A', 'A'),
+                RotorII::new('A', 'A'),
+                RotorIII::new('A', 'A'),
+                ReflectorB {},
+                uhr::Uhr::new(&cables, position).unwrap(),
+            );
+
+            let initial: String = "ADVANCEMINSK".into();
+            let encoded: String = machine.encipher_str(&initial).unwrap();
+
+            assert!(machine.permutation().is_involution());
+            assert!(machine.permutation().fixed_points().is_empty());
+
+            machine.reset();
+            let decoded: String = machine.encipher_str(&encoded).unwrap();
+
+            assert_eq!(initial, decoded);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_reset() {
         let mut machine = ArmyEnigma::new(
@@ -434,36 +845,244 @@ mod test {
 
         let input: String = "ADV".into();
         let expected: String = "PXB".into();
-        let output: String = input
-            .clone()
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
         let expected_settings = vec!['F', 'O', 'J'];
         assert_eq!(expected_settings, machine.settings());
 
-        let output: String = input
-            .clone()
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_ne!(expected, output);
         assert_ne!(expected_settings, machine.settings());
 
         machine.reset();
-        let output: String = input
-            .clone()
-            .chars()
-            .map(|in_char| machine.keypress(in_char).unwrap())
-            .collect();
+        let output: String = machine.encipher_str(&input).unwrap();
 
         assert_eq!(expected, output);
 
         let expected_settings = vec!['F', 'O', 'J'];
         assert_eq!(expected_settings, machine.settings());
     }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_snapshot_restore() {
+        let mut machine = ArmyEnigma::new(
+            RotorIV::new('G', 'V'),
+            RotorII::new('M', 'D'),
+            RotorV::new('Z', 'Y'),
+            ReflectorC,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+
+        for in_char in "CHECKPOINT".chars() {
+            machine.keypress(in_char).unwrap();
+        }
+
+        let checkpoint = machine.snapshot();
+        let first: String = machine.encipher_str("ALTERNATIVE").unwrap();
+
+        machine.restore(&checkpoint).unwrap();
+        assert_eq!(machine.snapshot(), checkpoint);
+
+        let second: String = machine.encipher_str("ALTERNATIVE").unwrap();
+        assert_eq!(first, second);
+
+        // The starting positions come back too, so reset still works.
+        machine.restore(&checkpoint).unwrap();
+        machine.reset();
+        assert_eq!(machine.settings(), vec!['V', 'D', 'Y']);
+
+        // A snapshot taken elsewhere carries its rings and plugboard with it.
+        let mut other = ArmyEnigma::new(
+            RotorIV::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorV::new('A', 'A'),
+            ReflectorC,
+            plugboard! {},
+        );
+        other.restore(&checkpoint).unwrap();
+        assert_eq!(other.snapshot(), checkpoint);
+
+        let third: String = other.encipher_str("ALTERNATIVE").unwrap();
+        assert_eq!(first, third);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_restore_errors() {
+        let mut machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB,
+            plugboard! {},
+        );
+        let before = machine.snapshot();
+
+        let mut snapshot = machine.snapshot();
+        snapshot.rotors.pop();
+        assert_eq!(
+            machine.restore(&snapshot),
+            Err(EnigmaError::Snapshot(snapshot::SnapshotError::RotorCount {
+                expected: 3,
+                found: 2
+            }))
+        );
+
+        let mut snapshot = machine.snapshot();
+        snapshot.rotors[0].position = 'Q';
+        snapshot.stecker = Some(Permutation::shift(1));
+        assert_eq!(
+            machine.restore(&snapshot),
+            Err(EnigmaError::Snapshot(snapshot::SnapshotError::Stecker))
+        );
+        assert_eq!(machine.snapshot(), before);
+
+        // A snapshot of I-II-III does not fit a machine set up IV-II-V.
+        let mut other = ArmyEnigma::new(
+            RotorIV::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorV::new('A', 'A'),
+            ReflectorB,
+            plugboard! {},
+        );
+        assert_eq!(
+            other.restore(&before),
+            Err(EnigmaError::Snapshot(snapshot::SnapshotError::WrongRotor {
+                slot: 1,
+                expected: "IV"
+            }))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_machine_settings() {
+        let mut machine = ArmyEnigma::new(
+            RotorIV::new('G', 'V'),
+            RotorII::new('M', 'D'),
+            RotorV::new('Z', 'Y'),
+            ReflectorC,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+
+        let settings = machine.machine_settings();
+        assert_eq!(
+            settings.to_string(),
+            "C IV-II-V 07-13-26 VDY AT BL DF GJ HM NW OP QY RZ VX"
+        );
+        assert_eq!(settings.to_string().parse(), Ok(settings));
+
+        // V carries its neighbour when it leaves Z, so after one keypress the
+        // right-hand rotor sits at its notch.
+        machine.keypress('A').unwrap();
+        let settings = machine.machine_settings();
+        assert_eq!(settings.positions(), vec!['V', 'D', 'Z']);
+        assert_eq!(
+            settings
+                .rotors
+                .iter()
+                .map(|rotor| rotor.at_notch)
+                .collect::<Vec<_>>(),
+            vec![false, false, true]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_reconfigure() {
+        let mut machine = ArmyEnigma::new(
+            RotorIV::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorV::new('A', 'A'),
+            ReflectorC,
+            None,
+        );
+        let mut expected = ArmyEnigma::new(
+            RotorIV::new('G', 'V'),
+            RotorII::new('M', 'D'),
+            RotorV::new('Z', 'Y'),
+            ReflectorC,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+
+        machine.keypress('A').unwrap();
+        machine.set_ring_settings(&['G', 'M', 'Z']).unwrap();
+        machine.set_positions(&['V', 'D', 'Y']).unwrap();
+        machine
+            .set_plugboard(&[
+                ('A', 'T'),
+                ('B', 'L'),
+                ('D', 'F'),
+                ('G', 'J'),
+                ('H', 'M'),
+                ('N', 'W'),
+                ('O', 'P'),
+                ('Q', 'Y'),
+                ('R', 'Z'),
+                ('V', 'X'),
+            ])
+            .unwrap();
+
+        for in_char in "REUSETHEALLOCATION".chars() {
+            assert_eq!(machine.keypress(in_char), expected.keypress(in_char));
+        }
+
+        // The new positions are where a reset goes back to.
+        machine.reset();
+        expected.reset();
+        assert_eq!(machine.settings(), vec!['V', 'D', 'Y']);
+        assert_eq!(machine.permutation(), expected.permutation());
+
+        // Rewiring in place, and taking the plugboard out.
+        machine.stecker_mut().unwrap().remove_pair('A').unwrap();
+        assert_eq!(machine.plugboard_transpose('A'), 'A');
+        machine.set_stecker(None);
+        assert_eq!(machine.plugboard_transpose('B'), 'B');
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_reconfigure_errors() {
+        let mut machine = ArmyEnigma::new(
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB,
+            plugboard!("AB"),
+        );
+        let before = machine.machine_settings();
+
+        assert_eq!(
+            machine.set_ring_settings(&['B', 'C']),
+            Err(EnigmaError::RotorCount {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            machine.set_ring_settings(&['B', 'C', '4']),
+            Err(EnigmaError::Rotor {
+                slot: 3,
+                source: RotorError::InvalidRingSetting('4')
+            })
+        );
+        assert_eq!(
+            machine.set_positions(&['Q', 'e', 'V']),
+            Err(EnigmaError::Rotor {
+                slot: 2,
+                source: RotorError::InvalidPosition('e')
+            })
+        );
+        assert_eq!(
+            machine.set_plugboard(&[('C', 'D'), ('D', 'E')]),
+            Err(EnigmaError::Plugboard(
+                plugboard::PlugboardError::CharacterAlreadyWired('D')
+            ))
+        );
+        assert_eq!(machine.machine_settings(), before);
+    }
 }
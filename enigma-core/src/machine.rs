@@ -0,0 +1,480 @@
+// src/machine.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::permutation::Permutation;
+use crate::plugboard::{Plugboard, Stecker};
+use crate::reflectors::Reflector;
+use crate::rotors::RotorEncode;
+use crate::settings::{MachineSettings, RotorSettings};
+use crate::snapshot::{self, RotorState, Snapshot};
+use crate::stepping::{Stepping, Wheel, Wheels};
+use crate::{_check_input, _set_positions, _set_ring_settings, Enigma, EnigmaError};
+
+use alloc::{boxed::Box, vec::Vec};
+
+/// One rotor position in a [`RotorMachine`], and whether the stepping
+/// mechanism reaches it.
+pub struct Slot {
+    rotor: Box<dyn RotorEncode>,
+    moves: bool,
+}
+
+impl Slot {
+    /// A rotor driven by the machine's stepping mechanism.
+    pub fn moving<R: RotorEncode + 'static>(rotor: R) -> Self {
+        Slot {
+            rotor: Box::new(rotor),
+            moves: true,
+        }
+    }
+
+    /// A rotor that is set by hand and stays put, such as an M4 Greek wheel.
+    pub fn fixed<R: RotorEncode + 'static>(rotor: R) -> Self {
+        Slot {
+            rotor: Box::new(rotor),
+            moves: false,
+        }
+    }
+
+    /// A rotor that has already been boxed, such as one built by name from a
+    /// [`crate::registry::Registry`].
+    pub fn boxed(rotor: Box<dyn RotorEncode>, moves: bool) -> Self {
+        Slot { rotor, moves }
+    }
+
+    pub fn rotor(&self) -> &dyn RotorEncode {
+        self.rotor.as_ref()
+    }
+
+    pub fn moves(&self) -> bool {
+        self.moves
+    }
+}
+
+impl Wheel for Slot {
+    fn position(&self) -> char {
+        self.rotor.position()
+    }
+
+    fn at_notch(&self) -> bool {
+        self.rotor.at_notch()
+    }
+
+    fn advance(&mut self) {
+        self.rotor.advance()
+    }
+}
+
+/// The slots of a machine that its stepping mechanism reaches.
+struct MovingSlots<'a>(&'a mut [Slot]);
+
+impl Wheels for MovingSlots<'_> {
+    fn count(&self) -> usize {
+        self.0.iter().filter(|slot| slot.moves).count()
+    }
+
+    fn wheel(&mut self, n: usize) -> &mut dyn Wheel {
+        self.0
+            .iter_mut()
+            .filter(|slot| slot.moves)
+            .nth(n)
+            .expect("there are as many moving slots as the count gives")
+    }
+}
+
+/// A machine with any number of rotors, listed from the reflector end to the
+/// keyboard end, any of which may be fixed.
+pub struct RotorMachine<D, E, S> {
+    slots: Vec<Slot>,
+    reflector: D,
+    plugboard: Option<E>,
+    stepping: S,
+}
+
+impl<D: Reflector, S: Stepping> RotorMachine<D, Plugboard, S> {
+    pub fn new(
+        slots: Vec<Slot>,
+        reflector: D,
+        stepping: S,
+        plugboard: impl Into<Option<Plugboard>>,
+    ) -> Self {
+        RotorMachine {
+            slots,
+            reflector,
+            plugboard: plugboard.into(),
+            stepping,
+        }
+    }
+
+    /// Replace every cable on the plugboard. Nothing changes if the pairs
+    /// are not a valid wiring.
+    pub fn set_plugboard(&mut self, pairs: &[(char, char)]) -> Result<(), EnigmaError> {
+        self.plugboard = Some(Plugboard::new(pairs)?);
+
+        Ok(())
+    }
+}
+
+impl<D: Reflector, E: Stecker, S: Stepping> RotorMachine<D, E, S> {
+    /// Build a machine around any stecker, such as an [`crate::uhr::Uhr`].
+    pub fn with_stecker(slots: Vec<Slot>, reflector: D, stepping: S, stecker: E) -> Self {
+        RotorMachine {
+            slots,
+            reflector,
+            plugboard: Some(stecker),
+            stepping,
+        }
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    /// Swap the rotor at `index`, counted from the left from 0 as in
+    /// [`RotorMachine::slots`], for another, and hand back the one taken out.
+    /// The slot keeps stepping, or not, as before. Errors name the slot
+    /// counted from 1, as every other rotor error does.
+    pub fn set_rotor(
+        &mut self,
+        index: usize,
+        rotor: Box<dyn RotorEncode>,
+    ) -> Result<Box<dyn RotorEncode>, EnigmaError> {
+        let slot = self
+            .slots
+            .get_mut(index)
+            .ok_or(EnigmaError::NoSuchSlot(index + 1))?;
+
+        Ok(core::mem::replace(&mut slot.rotor, rotor))
+    }
+
+    /// Swap the reflector for another, handing back the one taken out.
+    pub fn set_reflector(&mut self, reflector: D) -> D {
+        core::mem::replace(&mut self.reflector, reflector)
+    }
+
+    /// The stecker, to be rewired in place, e.g. with
+    /// [`Plugboard::add_pair`].
+    pub fn stecker_mut(&mut self) -> Option<&mut E> {
+        self.plugboard.as_mut()
+    }
+
+    /// Swap in a different stecker, or take it out altogether.
+    pub fn set_stecker(&mut self, stecker: impl Into<Option<E>>) {
+        self.plugboard = stecker.into();
+    }
+
+    fn _rotors_mut(&mut self) -> Vec<&mut dyn RotorEncode> {
+        self.slots
+            .iter_mut()
+            .map(|slot| -> &mut dyn RotorEncode { slot.rotor.as_mut() })
+            .collect()
+    }
+}
+
+impl<D: Reflector, E: Stecker, S: Stepping> Enigma for RotorMachine<D, E, S> {
+    fn reset(&mut self) {
+        for slot in self.slots.iter_mut() {
+            slot.rotor.reset();
+        }
+    }
+
+    fn keypress(&mut self, input: char) -> Result<char, EnigmaError> {
+        _check_input(input)?;
+
+        self.stepping.step_wheels(&mut MovingSlots(&mut self.slots));
+
+        let output = self.plugboard_transpose(input);
+        let output = self
+            .slots
+            .iter()
+            .rev()
+            .fold(output, |c, slot| slot.rotor.transpose_in(c));
+        let output = self.reflector.transpose(output);
+        let output = self
+            .slots
+            .iter()
+            .fold(output, |c, slot| slot.rotor.transpose_out(c));
+        let output = match self.plugboard {
+            Some(ref pb) => pb.transpose_out(output),
+            None => output,
+        };
+
+        Ok(output)
+    }
+
+    fn plugboard_transpose(&self, input: char) -> char {
+        match self.plugboard {
+            Some(ref pb) => pb.transpose_in(input),
+            None => input,
+        }
+    }
+
+    fn settings(&self) -> Vec<char> {
+        self.slots
+            .iter()
+            .map(|slot| slot.rotor.position())
+            .collect()
+    }
+
+    fn set_ring_settings(&mut self, ring_settings: &[char]) -> Result<(), EnigmaError> {
+        _set_ring_settings(&mut self._rotors_mut(), ring_settings)
+    }
+
+    fn set_positions(&mut self, positions: &[char]) -> Result<(), EnigmaError> {
+        _set_positions(&mut self._rotors_mut(), positions)
+    }
+
+    fn machine_settings(&self) -> MachineSettings {
+        MachineSettings::with_reflector(
+            &self.reflector,
+            self.slots
+                .iter()
+                .map(|slot| RotorSettings::of(&*slot.rotor))
+                .collect(),
+            snapshot::_snapshot_stecker(&self.plugboard),
+        )
+    }
+
+    fn permutation(&self) -> Permutation {
+        let plugboard = match self.plugboard {
+            Some(ref pb) => pb.permutation(),
+            None => Permutation::identity(),
+        };
+        let rotors = self
+            .slots
+            .iter()
+            .rev()
+            .fold(Permutation::identity(), |perm, slot| {
+                perm.then(&slot.rotor.permutation())
+            });
+
+        plugboard
+            .then(&rotors)
+            .then(&self.reflector.permutation())
+            .then(&rotors.inverse())
+            .then(&plugboard.inverse())
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rotors: self
+                .slots
+                .iter()
+                .map(|slot| RotorState::of(slot.rotor.as_ref()))
+                .collect(),
+            reflector: None,
+            stecker: snapshot::_snapshot_stecker(&self.plugboard),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), EnigmaError> {
+        let names: Vec<&'static str> = self.slots.iter().map(|slot| slot.rotor.name()).collect();
+        snapshot.check(&names, false)?;
+        snapshot::_restore_stecker(&mut self.plugboard, snapshot.stecker.as_ref())?;
+
+        for (slot, state) in self.slots.iter_mut().zip(snapshot.rotors.iter()) {
+            state.apply(slot.rotor.as_mut());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reflectors::*;
+    use crate::rotors::*;
+    use crate::stepping::Pawl;
+    use crate::{plugboard, ArmyEnigma};
+
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_three_rotors() {
+        let mut machine = RotorMachine::new(
+            vec![
+                Slot::moving(RotorI::new('A', 'A')),
+                Slot::moving(RotorII::new('A', 'A')),
+                Slot::moving(RotorIII::new('A', 'A')),
+            ],
+            ReflectorB,
+            Pawl,
+            None,
+        );
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "BDZGO");
+        assert_eq!(machine.settings(), vec!['A', 'A', 'F']);
+
+        machine.reset();
+        assert_eq!(machine.settings(), vec!['A', 'A', 'A']);
+        assert_eq!(machine.encipher_str("BDZGO").unwrap(), "AAAAA");
+    }
+
+    #[test]
+    fn test_matches_army_enigma() {
+        let mut army = ArmyEnigma::new(
+            RotorIV::new('G', 'V'),
+            RotorII::new('M', 'D'),
+            RotorV::new('Z', 'Y'),
+            ReflectorC,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+        let mut machine = RotorMachine::new(
+            vec![
+                Slot::moving(RotorIV::new('G', 'V')),
+                Slot::moving(RotorII::new('M', 'D')),
+                Slot::moving(RotorV::new('Z', 'Y')),
+            ],
+            ReflectorC,
+            Pawl,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+
+        for in_char in "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG".chars() {
+            assert_eq!(machine.permutation(), army.permutation());
+            assert_eq!(machine.keypress(in_char), army.keypress(in_char));
+            assert_eq!(machine.settings(), army.settings());
+        }
+    }
+
+    #[test]
+    fn test_set_rotor() {
+        let mut machine = RotorMachine::new(
+            vec![
+                Slot::moving(RotorV::new('A', 'A')),
+                Slot::moving(RotorII::new('A', 'A')),
+                Slot::moving(RotorIII::new('A', 'A')),
+            ],
+            Box::new(ReflectorC) as Box<dyn Reflector>,
+            Pawl,
+            None,
+        );
+
+        let taken = machine
+            .set_rotor(0, Box::new(RotorI::new('A', 'A')))
+            .unwrap();
+        assert_eq!(taken.name(), "V");
+        machine.set_reflector(Box::new(ReflectorB));
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "BDZGO");
+        assert!(machine.slots()[0].moves());
+
+        assert!(matches!(
+            machine.set_rotor(3, Box::new(RotorIV::new('A', 'A'))),
+            Err(EnigmaError::NoSuchSlot(4))
+        ));
+        assert_eq!(machine.slots().len(), 3);
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let mut machine = RotorMachine::new(
+            vec![
+                Slot::fixed(RotorBeta::new('A', 'A')),
+                Slot::moving(RotorI::new('A', 'A')),
+                Slot::moving(RotorII::new('A', 'A')),
+                Slot::moving(RotorIII::new('A', 'A')),
+            ],
+            ReflectorBThin,
+            Pawl,
+            None,
+        );
+
+        machine.set_ring_settings(&['A', 'B', 'C', 'D']).unwrap();
+        machine.set_positions(&['A', 'Q', 'E', 'V']).unwrap();
+        machine.set_plugboard(&[('A', 'Z')]).unwrap();
+
+        assert_eq!(
+            machine.machine_settings().to_string(),
+            "B-thin Beta-I-II-III 01-02-03-04 AQEV AZ"
+        );
+        assert_eq!(
+            machine.set_positions(&['A', 'Q', 'E']),
+            Err(EnigmaError::RotorCount {
+                expected: 4,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_four_rotors() {
+        // An M4 with Beta at A and the thin B reflector is the three-rotor
+        // machine with reflector B, which is how it kept talking to the M3.
+        let mut m4 = RotorMachine::new(
+            vec![
+                Slot::fixed(RotorBeta::new('A', 'A')),
+                Slot::moving(RotorI::new('A', 'A')),
+                Slot::moving(RotorII::new('A', 'A')),
+                Slot::moving(RotorIII::new('A', 'A')),
+            ],
+            ReflectorBThin,
+            Pawl,
+            None,
+        );
+
+        assert_eq!(m4.encipher_str("AAAAA").unwrap(), "BDZGO");
+        assert_eq!(m4.settings(), vec!['A', 'A', 'A', 'F']);
+
+        let mut m4 = RotorMachine::new(
+            vec![
+                Slot::fixed(RotorBeta::new('A', 'A')),
+                Slot::moving(RotorIV::new('G', 'V')),
+                Slot::moving(RotorII::new('M', 'D')),
+                Slot::moving(RotorV::new('Z', 'Y')),
+            ],
+            ReflectorBThin,
+            Pawl,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+        let mut m3 = ArmyEnigma::new(
+            RotorIV::new('G', 'V'),
+            RotorII::new('M', 'D'),
+            RotorV::new('Z', 'Y'),
+            ReflectorB,
+            plugboard!("AT BL DF GJ HM NW OP QY RZ VX"),
+        );
+
+        assert_eq!(
+            m4.encipher_str("KEINEBESONDERENEREIGNISSE").unwrap(),
+            m3.encipher_str("KEINEBESONDERENEREIGNISSE").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_five_rotors() {
+        let mut machine = RotorMachine::new(
+            vec![
+                Slot::moving(RotorV::new('A', 'Z')),
+                Slot::moving(RotorIV::new('A', 'I')),
+                Slot::fixed(RotorVIII::new('C', 'M')),
+                Slot::moving(RotorII::new('A', 'D')),
+                Slot::moving(RotorIII::new('A', 'U')),
+            ],
+            ReflectorB,
+            Pawl,
+            plugboard!("AB CD"),
+        );
+
+        // IV lands on its own notch on the third keypress, so the pawl
+        // behind it pushes V on the fourth; the fixed VIII never moves.
+        let ciphertext = machine.encipher_str("EXPERIMENTALDESIGN").unwrap();
+        assert_eq!(machine.slots()[2].rotor().position(), 'M');
+        assert!(!machine.slots()[2].moves());
+        assert_eq!(machine.settings(), vec!['A', 'K', 'M', 'F', 'M']);
+
+        machine.reset();
+        assert_eq!(
+            machine.encipher_str(&ciphertext).unwrap(),
+            "EXPERIMENTALDESIGN"
+        );
+    }
+}
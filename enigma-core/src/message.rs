@@ -0,0 +1,519 @@
+// src/message.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! Laying out ciphertext the way it went over the air: a header line giving
+//! the time, the part, the letter count and the indicator, followed by the
+//! text in five-letter groups.
+//!
+//! ```text
+//! 1230 = 2tle 1tl = 18 = WZA UHL =
+//! RFUGZ EDPUD NRGYS ZRC
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+/// Why a header or message could not be read back from text. No machine is
+/// involved, so it is not an [`EnigmaError`](crate::EnigmaError).
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum MessageError {
+    /// The header has no letter count.
+    MissingLetterCount,
+    /// A field of the header could not be read.
+    InvalidHeader(String),
+    /// The text is not as long as the header says.
+    LetterCount {
+        expected: usize,
+        found: usize,
+    },
+    InvalidCharacter(char),
+}
+
+impl Error for MessageError {}
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::MissingLetterCount => write!(f, "the header has no letter count"),
+            MessageError::InvalidHeader(field) => {
+                write!(f, "'{field}' is not a valid header field")
+            }
+            MessageError::LetterCount { expected, found } => {
+                write!(f, "the header gives {expected} letters, found {found}")
+            }
+            MessageError::InvalidCharacter(c) => {
+                write!(f, "'{c}' cannot appear in the text of a message")
+            }
+        }
+    }
+}
+
+/// Just the letters of some laid-out text, in uppercase.
+pub fn raw(text: &str) -> String {
+    text.chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// How to lay out the letters of a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    group_size: Option<usize>,
+    line_width: Option<usize>,
+}
+
+impl Default for Layout {
+    /// Five-letter groups on one line.
+    fn default() -> Self {
+        Layout {
+            group_size: Some(5),
+            line_width: None,
+        }
+    }
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The letters in one unbroken run, for the `io` adapters when only a
+    /// line width is asked for.
+    #[cfg(feature = "std")]
+    pub(crate) fn ungrouped() -> Self {
+        Layout {
+            group_size: None,
+            line_width: None,
+        }
+    }
+
+    pub fn group_size(mut self, group_size: usize) -> Self {
+        self.group_size = Some(group_size.max(1));
+        self
+    }
+
+    /// Start a new line rather than run a group past `line_width` columns.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = Some(line_width.max(1));
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn is_grouped(&self) -> bool {
+        self.group_size.is_some()
+    }
+
+    /// The number of groups the letters of `text` make up, counting a short
+    /// last group.
+    pub fn group_count(&self, text: &str) -> usize {
+        let letters = raw(text).len();
+
+        match self.group_size {
+            Some(size) => letters.div_ceil(size),
+            None => usize::from(letters > 0),
+        }
+    }
+
+    /// Lay out the letters of `text` in groups, ignoring anything else.
+    pub fn format(&self, text: &str) -> String {
+        let letters = raw(text);
+        let mut output = String::with_capacity(letters.len() + letters.len() / 4);
+        let mut cursor = Cursor::default();
+
+        for letter in letters.chars() {
+            output.extend(cursor.letter(self));
+            output.push(letter);
+        }
+
+        output
+    }
+}
+
+/// Where a layout has got to, for laying out letters one at a time.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Cursor {
+    column: usize,
+    in_group: usize,
+}
+
+impl Cursor {
+    /// The space or newline to put before the next letter, if any, under
+    /// `layout`. Groups are never broken, even when longer than a line.
+    pub(crate) fn letter(&mut self, layout: &Layout) -> Option<char> {
+        let separator = match (layout.group_size, layout.line_width) {
+            (Some(size), Some(width))
+                if self.in_group == size && self.column + 1 + size > width =>
+            {
+                Some('\n')
+            }
+            (Some(size), _) if self.in_group == size => Some(' '),
+            (None, Some(width)) if self.column >= width => Some('\n'),
+            _ => None,
+        };
+
+        match separator {
+            Some('\n') => self.column = 0,
+            Some(_) => self.column += 1,
+            None => {}
+        }
+        if separator.is_some() {
+            self.in_group = 0;
+        }
+
+        self.column += 1;
+        self.in_group += 1;
+
+        separator
+    }
+
+    /// Count a character that was passed through as it was.
+    #[cfg(feature = "std")]
+    pub(crate) fn other(&mut self, c: char) {
+        if c == '\n' {
+            self.column = 0;
+            self.in_group = 0;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+/// Which part of a longer message this is, counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Part {
+    pub number: usize,
+    pub total: usize,
+}
+
+/// The preamble sent ahead of the text, e.g. `1230 = 2tle 1tl = 149 = WZA UHL =`
+/// for the first of two parts, 149 letters long, sent at 12:30.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub time: Option<String>,
+    pub part: Option<Part>,
+    pub letters: usize,
+    /// The groups that let the receiver recover the message key.
+    pub indicator: Option<String>,
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(time) = &self.time {
+            write!(f, "{time} = ")?;
+        }
+
+        if let Some(part) = &self.part {
+            write!(f, "{}tle {}tl = ", part.total, part.number)?;
+        }
+
+        write!(f, "{} =", self.letters)?;
+
+        if let Some(indicator) = &self.indicator {
+            write!(f, " {indicator} =")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn _parse_part(field: &str) -> Option<Part> {
+    let lower = field.to_ascii_lowercase();
+    let mut total = None;
+    let mut number = None;
+
+    // Only `<digits>tle` and `<digits>tl`: indicators like `ATL QRS` are
+    // letters that happen to spell the same suffixes.
+    let count = |n: &str| match !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) {
+        true => n.parse().ok(),
+        false => None,
+    };
+
+    for word in lower.split_whitespace() {
+        if let Some(n) = word.strip_suffix("tle") {
+            total = Some(count(n)?);
+        } else if let Some(n) = word.strip_suffix("tl") {
+            number = Some(count(n)?);
+        } else {
+            return None;
+        }
+    }
+
+    Some(Part {
+        number: number?,
+        total: total?,
+    })
+}
+
+impl FromStr for Header {
+    type Err = MessageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut header = Header::default();
+        let mut numbers = Vec::new();
+
+        for field in s.split('=').map(str::trim).filter(|f| !f.is_empty()) {
+            let invalid = || MessageError::InvalidHeader(field.to_owned());
+
+            if field.chars().all(|c| c.is_ascii_digit()) {
+                numbers.push(field);
+            } else if let Some(part) = _parse_part(field) {
+                header.part = Some(part);
+            } else if field.chars().all(|c| c.is_ascii_alphabetic() || c == ' ') {
+                header.indicator = Some(field.to_ascii_uppercase());
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        // The time comes first where there is one; the letter count always
+        // follows it.
+        let letters = match numbers[..] {
+            [time, letters] => {
+                header.time = Some(time.to_owned());
+                letters
+            }
+            [letters] => letters,
+            [] => return Err(MessageError::MissingLetterCount),
+            [_, _, extra, ..] => return Err(MessageError::InvalidHeader(extra.to_owned())),
+        };
+        header.letters = letters
+            .parse()
+            .map_err(|_| MessageError::InvalidHeader(letters.to_owned()))?;
+
+        Ok(header)
+    }
+}
+
+/// Ciphertext ready to send, with its header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub header: Header,
+    /// The letters of the message, without any layout.
+    pub text: String,
+}
+
+impl Message {
+    /// A message of the letters of `text`, with the letter count filled in.
+    pub fn new(text: &str) -> Self {
+        let text = raw(text);
+
+        Message {
+            header: Header {
+                letters: text.len(),
+                ..Header::default()
+            },
+            text,
+        }
+    }
+
+    pub fn time(mut self, time: &str) -> Self {
+        self.header.time = Some(time.to_owned());
+        self
+    }
+
+    pub fn part(mut self, number: usize, total: usize) -> Self {
+        self.header.part = Some(Part { number, total });
+        self
+    }
+
+    /// The indicator groups, in uppercase as they are read back.
+    pub fn indicator(mut self, indicator: &str) -> Self {
+        self.header.indicator = Some(indicator.to_ascii_uppercase());
+        self
+    }
+
+    /// The header line, then the text laid out as `layout` says.
+    pub fn format(&self, layout: &Layout) -> String {
+        let mut output = self.header.to_string();
+
+        if !self.text.is_empty() {
+            output.push('\n');
+            output.push_str(&layout.format(&self.text));
+        }
+
+        output
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&Layout::default()))
+    }
+}
+
+impl FromStr for Message {
+    type Err = MessageError;
+
+    /// Read a header line and the groups under it back into a message,
+    /// checking the letter count.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start();
+        let (header, body) = s.split_once('\n').unwrap_or((s, ""));
+        let header: Header = header.parse()?;
+
+        if let Some(c) = body
+            .chars()
+            .find(|c| !c.is_ascii_alphabetic() && !c.is_whitespace())
+        {
+            return Err(MessageError::InvalidCharacter(c));
+        }
+
+        let text = raw(body);
+        if text.len() != header.letters {
+            return Err(MessageError::LetterCount {
+                expected: header.letters,
+                found: text.len(),
+            });
+        }
+
+        Ok(Message { header, text })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEXT: &str = "RFUGZEDPUDNRGYSZRC";
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(Layout::new().format(TEXT), "RFUGZ EDPUD NRGYS ZRC");
+        assert_eq!(
+            Layout::new().line_width(11).format(TEXT),
+            "RFUGZ EDPUD\nNRGYS ZRC"
+        );
+        assert_eq!(
+            Layout::new()
+                .group_size(4)
+                .line_width(10)
+                .format("rfug zedp-ud"),
+            "RFUG ZEDP\nUD"
+        );
+        assert_eq!(
+            Layout::new().group_size(0).line_width(0).format("RFU"),
+            "R\nF\nU"
+        );
+        assert_eq!(
+            Layout::new().line_width(3).format(TEXT),
+            "RFUGZ\nEDPUD\nNRGYS\nZRC"
+        );
+        assert_eq!(Layout::new().format(""), "");
+        assert_eq!(Layout::new().group_count(TEXT), 4);
+        assert_eq!(Layout::new().group_count("RFUGZ EDPUD"), 2);
+    }
+
+    #[test]
+    fn test_header() {
+        let header = Header {
+            time: Some("1230".into()),
+            part: Some(Part {
+                number: 1,
+                total: 2,
+            }),
+            letters: 149,
+            indicator: Some("WZA UHL".into()),
+        };
+
+        assert_eq!(header.to_string(), "1230 = 2tle 1tl = 149 = WZA UHL =");
+        assert_eq!(header.to_string().parse(), Ok(header));
+        assert_eq!(
+            "= 49 = ehz tbs =".parse(),
+            Ok(Header {
+                letters: 49,
+                indicator: Some("EHZ TBS".into()),
+                ..Header::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_header_errors() {
+        assert_eq!(
+            "1230 = WZA UHL =".parse::<Header>(),
+            Ok(Header {
+                letters: 1230,
+                indicator: Some("WZA UHL".into()),
+                ..Header::default()
+            })
+        );
+        assert_eq!(
+            "WZA UHL =".parse::<Header>(),
+            Err(MessageError::MissingLetterCount)
+        );
+        assert_eq!(
+            "1230 = 1tl = 49 =".parse::<Header>(),
+            Err(MessageError::InvalidHeader("1tl".into()))
+        );
+        assert_eq!(
+            "2tle 1tl = 49 = ATL QRS =".parse::<Header>(),
+            Ok(Header {
+                part: Some(Part {
+                    number: 1,
+                    total: 2
+                }),
+                letters: 49,
+                indicator: Some("ATL QRS".into()),
+                ..Header::default()
+            })
+        );
+        assert_eq!(
+            "49 = ARC TLU =".parse::<Header>(),
+            Ok(Header {
+                letters: 49,
+                indicator: Some("ARC TLU".into()),
+                ..Header::default()
+            })
+        );
+        assert_eq!(
+            "12:30 = 49 =".parse::<Header>(),
+            Err(MessageError::InvalidHeader("12:30".into()))
+        );
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let message = Message::new(TEXT)
+            .time("1230")
+            .part(1, 2)
+            .indicator("WZA UHL");
+        let layout = Layout::new().line_width(11);
+
+        assert_eq!(
+            message.format(&layout),
+            "1230 = 2tle 1tl = 18 = WZA UHL =\nRFUGZ EDPUD\nNRGYS ZRC"
+        );
+        assert_eq!(message.format(&layout).parse(), Ok(message.clone()));
+        assert_eq!(message.to_string().parse(), Ok(message));
+
+        let message = Message::new(TEXT).indicator("wza uhl");
+        assert_eq!(message.header.indicator.as_deref(), Some("WZA UHL"));
+        assert_eq!(message.to_string().parse(), Ok(message));
+    }
+
+    #[test]
+    fn test_message_errors() {
+        assert_eq!(
+            "= 20 =\nRFUGZ EDPUD NRGYS ZRC".parse::<Message>(),
+            Err(MessageError::LetterCount {
+                expected: 20,
+                found: 18
+            })
+        );
+        assert_eq!(
+            "= 18 =\nRFUGZ EDPUD NRGYS ZR1".parse::<Message>(),
+            Err(MessageError::InvalidCharacter('1'))
+        );
+    }
+}
@@ -0,0 +1,156 @@
+// src/model.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub enum ModelError {
+    UnknownModel,
+}
+
+impl Error for ModelError {}
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::UnknownModel => write!(f, "not a known Enigma model"),
+        }
+    }
+}
+
+/// The machine models with wheel sets in this crate, so that one can be
+/// picked by name from a configuration file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Model {
+    /// The Army and Air Force Enigma I, with the naval wheels VI-VIII.
+    EnigmaI,
+    /// The four-rotor naval machine.
+    M4,
+    EnigmaD,
+    EnigmaK,
+    SwissK,
+    /// The Reichsbahn's rewired Enigma K.
+    Railway,
+    /// The Enigma T, made for Japan.
+    Tirpitz,
+    G312,
+    G260,
+}
+
+impl Model {
+    pub const ALL: [Model; 9] = [
+        Model::EnigmaI,
+        Model::M4,
+        Model::EnigmaD,
+        Model::EnigmaK,
+        Model::SwissK,
+        Model::Railway,
+        Model::Tirpitz,
+        Model::G312,
+        Model::G260,
+    ];
+
+    /// The name the model is usually known by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::EnigmaI => "Enigma I",
+            Model::M4 => "Enigma M4",
+            Model::EnigmaD => "Enigma D",
+            Model::EnigmaK => "Enigma K",
+            Model::SwissK => "Swiss-K",
+            Model::Railway => "Railway",
+            Model::Tirpitz => "Enigma T",
+            Model::G312 => "Enigma G-312",
+            Model::G260 => "Enigma G-260",
+        }
+    }
+
+    /// Other names accepted when parsing, besides [`Model::name`].
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Model::EnigmaI => &["I", "Army", "Wehrmacht", "M3"],
+            Model::M4 => &["M4", "Kriegsmarine"],
+            Model::EnigmaD => &["D"],
+            Model::EnigmaK => &["K"],
+            Model::SwissK => &["Swiss K", "K-Swiss"],
+            Model::Railway => &["Reichsbahn", "Rocket"],
+            Model::Tirpitz => &["T", "Tirpitz"],
+            Model::G312 => &["G-312", "G312", "Abwehr"],
+            Model::G260 => &["G-260", "G260"],
+        }
+    }
+
+    /// Whether the machine has a plugboard.
+    pub fn has_plugboard(&self) -> bool {
+        matches!(self, Model::EnigmaI | Model::M4)
+    }
+
+    /// Whether the reflector can be set to a position other than `A`.
+    pub fn has_settable_reflector(&self) -> bool {
+        !matches!(self, Model::EnigmaI | Model::M4)
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Model {
+    type Err = ModelError;
+
+    /// Matches a model's name or one of its aliases, ignoring case and
+    /// treating `-`, `_` and spaces alike.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let same = |name: &str| {
+            let normalize = |c: char| match c {
+                '-' | '_' => ' ',
+                c => c.to_ascii_uppercase(),
+            };
+
+            name.chars()
+                .map(normalize)
+                .eq(s.trim().chars().map(normalize))
+        };
+
+        Model::ALL
+            .iter()
+            .find(|model| same(model.name()) || model.aliases().iter().any(|a| same(a)))
+            .copied()
+            .ok_or(ModelError::UnknownModel)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("Railway".parse(), Ok(Model::Railway));
+        assert_eq!("reichsbahn".parse(), Ok(Model::Railway));
+        assert_eq!("enigma-t".parse(), Ok(Model::Tirpitz));
+        assert_eq!("SWISS_K".parse(), Ok(Model::SwissK));
+        assert_eq!(" g-312 ".parse(), Ok(Model::G312));
+        assert_eq!("Enigma Z".parse::<Model>(), Err(ModelError::UnknownModel));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_names_round_trip() {
+        for model in Model::ALL {
+            assert_eq!(model.to_string().parse(), Ok(model));
+        }
+    }
+}
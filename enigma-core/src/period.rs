@@ -0,0 +1,72 @@
+// src/period.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// The path the rotor positions take under repeated keypresses.
+///
+/// Not every starting position lies on the stepping cycle: a middle rotor set
+/// to its own notch double-steps away on the first keypress and is never seen
+/// there again. Those positions make up the `lead_in`; everything after is the
+/// cycle proper, which repeats forever.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepCycle {
+    pub lead_in: Vec<Vec<char>>,
+    pub states: Vec<Vec<char>>,
+}
+
+impl StepCycle {
+    /// The number of keypresses before the rotor positions repeat.
+    pub fn period(&self) -> usize {
+        self.states.len()
+    }
+}
+
+/// Follow `step` from `initial` until a position repeats.
+pub(crate) fn trace_cycle<F: FnMut() -> Vec<char>>(initial: Vec<char>, mut step: F) -> StepCycle {
+    let mut seen: BTreeMap<Vec<char>, usize> = BTreeMap::new();
+    let mut visited: Vec<Vec<char>> = Vec::new();
+    let mut state = initial;
+
+    while !seen.contains_key(&state) {
+        seen.insert(state.clone(), visited.len());
+        visited.push(state);
+        state = step();
+    }
+
+    let states = visited.split_off(seen[&state]);
+
+    StepCycle {
+        lead_in: visited,
+        states,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_trace_cycle() {
+        let mut counter = 0;
+        let cycle = trace_cycle(vec!['A'], || {
+            counter = match counter {
+                0 => 1,
+                n => (n % 3) + 1,
+            };
+            vec![(b'A' + counter) as char]
+        });
+
+        assert_eq!(cycle.lead_in, vec![vec!['A']]);
+        assert_eq!(cycle.states, vec![vec!['B'], vec!['C'], vec!['D']]);
+        assert_eq!(cycle.period(), 3);
+    }
+}
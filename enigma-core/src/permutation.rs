@@ -0,0 +1,355 @@
+// src/permutation.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::error::Error;
+use core::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum PermutationError {
+    InvalidLength(usize),
+    InvalidCharacter(char),
+    DuplicateCharacter(char),
+}
+
+impl Error for PermutationError {}
+impl fmt::Display for PermutationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermutationError::InvalidLength(len) => {
+                write!(f, "expected 26 characters, found {len}")
+            }
+            PermutationError::InvalidCharacter(c) => {
+                write!(f, "'{c}' is not an uppercase ASCII letter")
+            }
+            PermutationError::DuplicateCharacter(c) => {
+                write!(f, "'{c}' appears more than once")
+            }
+        }
+    }
+}
+
+fn _index(input: char) -> usize {
+    (input as u8 - b'A') as usize
+}
+
+fn _letter(index: u8) -> char {
+    (b'A' + index) as char
+}
+
+/// A bijection on the 26 letters `A`-`Z`.
+///
+/// Every component of the machine -- rotor, reflector, plugboard -- is one of
+/// these, and so is the machine as a whole at any fixed rotor position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Permutation([u8; 26]);
+
+impl Permutation {
+    pub fn identity() -> Self {
+        let mut table = [0u8; 26];
+
+        for (i, slot) in table.iter_mut().enumerate() {
+            *slot = i as u8;
+        }
+
+        Self(table)
+    }
+
+    /// The Caesar shift `x -> x + n (mod 26)`.
+    pub fn shift(n: i32) -> Self {
+        let mut table = [0u8; 26];
+
+        for (i, slot) in table.iter_mut().enumerate() {
+            *slot = (i as i32 + n).rem_euclid(26) as u8;
+        }
+
+        Self(table)
+    }
+
+    /// Build a permutation from a wiring string, where the character at index
+    /// `i` is the image of the `i`th letter of the alphabet.
+    pub fn from_wiring(wiring: &str) -> Result<Self, PermutationError> {
+        let count = wiring.chars().count();
+        if count != 26 {
+            return Err(PermutationError::InvalidLength(count));
+        }
+
+        Self::from_fn(|c| wiring.chars().nth(_index(c)).unwrap())
+    }
+
+    /// Build a permutation by evaluating `mapping` on every letter `A`-`Z`.
+    pub fn from_fn<F: Fn(char) -> char>(mapping: F) -> Result<Self, PermutationError> {
+        let mut table = [0u8; 26];
+        let mut seen = [false; 26];
+
+        for (i, slot) in table.iter_mut().enumerate() {
+            let image = match mapping(_letter(i as u8)) {
+                c @ 'A'..='Z' => c,
+                c => return Err(PermutationError::InvalidCharacter(c)),
+            };
+
+            if seen[_index(image)] {
+                return Err(PermutationError::DuplicateCharacter(image));
+            }
+
+            seen[_index(image)] = true;
+            *slot = _index(image) as u8;
+        }
+
+        Ok(Self(table))
+    }
+
+    /// The image of `input`. Anything outside `A`-`Z` is returned untouched.
+    pub fn apply(&self, input: char) -> char {
+        match input {
+            'A'..='Z' => _letter(self.0[_index(input)]),
+            _ => input,
+        }
+    }
+
+    /// The permutation that applies `self` first, then `next`.
+    pub fn then(&self, next: &Permutation) -> Self {
+        let mut table = [0u8; 26];
+
+        for (i, slot) in table.iter_mut().enumerate() {
+            *slot = next.0[self.0[i] as usize];
+        }
+
+        Self(table)
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut table = [0u8; 26];
+
+        for (i, image) in self.0.iter().enumerate() {
+            table[*image as usize] = i as u8;
+        }
+
+        Self(table)
+    }
+
+    /// Conjugate by a shift of `n`, i.e. `x -> self(x + n) - n`. This is how a
+    /// fixed wiring behaves once its rotor has turned `n` steps.
+    pub fn conjugate(&self, n: i32) -> Self {
+        Self::shift(n).then(self).then(&Self::shift(-n))
+    }
+
+    /// The disjoint cycles of this permutation, each starting from its lowest
+    /// letter, ordered by that letter. Fixed points appear as 1-cycles.
+    #[cfg(feature = "alloc")]
+    pub fn cycles(&self) -> Vec<Vec<char>> {
+        let mut visited = [false; 26];
+        let mut cycles = Vec::new();
+
+        for start in 0..26 {
+            if visited[start] {
+                continue;
+            }
+
+            let mut cycle = Vec::new();
+            let mut cur = start;
+
+            while !visited[cur] {
+                visited[cur] = true;
+                cycle.push(_letter(cur as u8));
+                cur = self.0[cur] as usize;
+            }
+
+            cycles.push(cycle);
+        }
+
+        cycles
+    }
+
+    /// The lengths of the cycles, longest first.
+    #[cfg(feature = "alloc")]
+    pub fn cycle_type(&self) -> Vec<usize> {
+        let mut lengths: Vec<usize> = self.cycles().iter().map(|c| c.len()).collect();
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+
+        lengths
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn fixed_points(&self) -> Vec<char> {
+        ('A'..='Z').filter(|c| self.is_fixed(*c)).collect()
+    }
+
+    pub fn is_fixed(&self, input: char) -> bool {
+        self.apply(input) == input
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    pub fn is_involution(&self) -> bool {
+        self.then(self).is_identity()
+    }
+
+    /// The wiring string accepted by [`Permutation::from_wiring`].
+    #[cfg(feature = "alloc")]
+    pub fn wiring(&self) -> String {
+        self.0.iter().map(|i| _letter(*i)).collect()
+    }
+}
+
+impl Default for Permutation {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl fmt::Display for Permutation {
+    /// Cycle notation, omitting fixed points, e.g. `(AE)(BJ)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_identity() {
+            return write!(f, "()");
+        }
+
+        let mut visited = [false; 26];
+
+        for start in 0..26 {
+            if visited[start] || self.0[start] as usize == start {
+                continue;
+            }
+
+            let mut cur = start;
+
+            write!(f, "(")?;
+            while !visited[cur] {
+                visited[cur] = true;
+                write!(f, "{}", _letter(cur as u8))?;
+                cur = self.0[cur] as usize;
+            }
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serialized as its wiring string, e.g. `"EKMFLGDQVZNTOWYHXUSPAIBRCJ"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Permutation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.wiring())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Permutation {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wiring = String::deserialize(deserializer)?;
+
+        Permutation::from_wiring(&wiring).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use alloc::{format, vec};
+
+    const ROTOR_I: &str = "EKMFLGDQVZNTOWYHXUSPAIBRCJ";
+    #[cfg(feature = "alloc")]
+    const REFLECTOR_B: &str = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_from_wiring() {
+        let perm = Permutation::from_wiring(ROTOR_I).unwrap();
+
+        assert_eq!(perm.apply('A'), 'E');
+        assert_eq!(perm.apply('Z'), 'J');
+        assert_eq!(perm.apply(' '), ' ');
+        assert_eq!(perm.wiring(), ROTOR_I);
+    }
+
+    #[test]
+    fn test_from_wiring_errors() {
+        assert_eq!(
+            Permutation::from_wiring("ABC"),
+            Err(PermutationError::InvalidLength(3))
+        );
+        assert_eq!(
+            Permutation::from_wiring("AACDEFGHIJKLMNOPQRSTUVWXYZ"),
+            Err(PermutationError::DuplicateCharacter('A'))
+        );
+        assert_eq!(
+            Permutation::from_wiring("aBCDEFGHIJKLMNOPQRSTUVWXYZ"),
+            Err(PermutationError::InvalidCharacter('a'))
+        );
+    }
+
+    #[test]
+    fn test_inverse_and_then() {
+        let perm = Permutation::from_wiring(ROTOR_I).unwrap();
+        let inverse = perm.inverse();
+
+        assert_eq!(inverse.apply('E'), 'A');
+        assert!(perm.then(&inverse).is_identity());
+        assert!(inverse.then(&perm).is_identity());
+
+        let shift = Permutation::shift(1);
+        assert_eq!(perm.then(&shift).apply('A'), 'F');
+        assert_eq!(shift.then(&perm).apply('A'), 'K');
+    }
+
+    #[test]
+    fn test_conjugate() {
+        let perm = Permutation::from_wiring(ROTOR_I).unwrap();
+
+        assert_eq!(perm.conjugate(0), perm);
+        assert_eq!(perm.conjugate(26), perm);
+        // Rotor I turned one step: A enters at B, leaves K, and exits as J.
+        assert_eq!(perm.conjugate(1).apply('A'), 'J');
+        assert_eq!(perm.conjugate(1).conjugate(-1), perm);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_cycles() {
+        let perm = Permutation::from_wiring(REFLECTOR_B).unwrap();
+
+        assert!(perm.is_involution());
+        assert!(perm.fixed_points().is_empty());
+        assert_eq!(perm.cycle_type(), vec![2; 13]);
+        assert_eq!(perm.cycles()[0], vec!['A', 'Y']);
+
+        let perm = Permutation::from_wiring(ROTOR_I).unwrap();
+        let total: usize = perm.cycle_type().iter().sum();
+
+        assert!(!perm.is_involution());
+        assert_eq!(total, 26);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_fixed_points() {
+        let perm = Permutation::identity();
+
+        assert_eq!(perm.fixed_points().len(), 26);
+        assert!(perm.is_fixed('Q'));
+        assert_eq!(format!("{perm}"), "()");
+
+        let perm = Permutation::from_fn(|c| match c {
+            'A' => 'B',
+            'B' => 'A',
+            c => c,
+        })
+        .unwrap();
+
+        assert_eq!(perm.fixed_points().len(), 24);
+        assert!(!perm.is_fixed('A'));
+        assert_eq!(format!("{perm}"), "(AB)");
+    }
+}
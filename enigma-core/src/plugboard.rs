@@ -7,14 +7,24 @@
 // file may not be copied, modified, or distributed except according to those
 // terms.
 
+use crate::permutation::Permutation;
+use crate::snapshot::SnapshotError;
+
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::error::Error;
-use std::fmt;
 
 #[derive(Debug, PartialEq)]
 pub enum PlugboardError {
     InvalidCharacter((char, char)),
     CharacterAlreadyWired(char),
+    CharacterNotWired(char),
+    SelfWired(char),
+    NotReciprocal((char, char)),
+    /// The `n`th group of a key-sheet string, counting from 1, is not a pair.
+    MalformedPair(usize),
 }
 
 impl Error for PlugboardError {}
@@ -27,84 +37,280 @@ impl fmt::Display for PlugboardError {
             PlugboardError::CharacterAlreadyWired(c) => {
                 write!(f, "'{c}' already wired to the board")
             }
+            PlugboardError::CharacterNotWired(c) => {
+                write!(f, "'{c}' is not wired to the board")
+            }
+            PlugboardError::NotReciprocal((l, r)) => {
+                write!(f, "'{l}' is wired to '{r}', but '{r}' is not wired back")
+            }
+            PlugboardError::SelfWired(c) => {
+                write!(f, "'{c}' cannot be wired to itself")
+            }
+            PlugboardError::MalformedPair(n) => {
+                write!(f, "group {n} is not a pair of letters")
+            }
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Plugboard(HashMap<char, char>);
+/// The substitution between the keyboard and the entry wheel. A plain
+/// plugboard is reciprocal, so both directions are the same, but attachments
+/// such as the Uhr are not.
+pub trait Stecker {
+    /// The substitution on the way from the keyboard to the rotors.
+    fn transpose_in(&self, input: char) -> char;
+    /// The substitution on the way from the rotors to the lampboard.
+    fn transpose_out(&self, input: char) -> char;
+    /// The substitution on the way in, as a permutation.
+    fn permutation(&self) -> Permutation;
+
+    /// Set the stecker up to make `permutation` on the way in, as when
+    /// restoring a snapshot. By default only the substitution it already
+    /// makes is accepted.
+    fn restore(&mut self, permutation: &Permutation) -> Result<(), SnapshotError> {
+        match self.permutation() == *permutation {
+            true => Ok(()),
+            false => Err(SnapshotError::Stecker),
+        }
+    }
+}
+
+fn _index(letter: char) -> usize {
+    (letter as u8 - b'A') as usize
+}
+
+/// A fixed 26-entry table, where unplugged letters map to themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Plugboard([u8; 26]);
 
 impl Plugboard {
+    /// A board with a cable for each pair. A letter paired with itself is
+    /// left unplugged, as it always has been here, but still counts as used.
     pub fn new(pairs: &[(char, char)]) -> Result<Self, PlugboardError> {
-        let mut intermediate: HashMap<char, char> = HashMap::new();
+        let mut board = Self::default();
+        let mut self_paired = 0u32;
 
         for (left, right) in pairs.iter().copied() {
-            match (left, right) {
-                ('A'..='Z', 'A'..='Z') => {}
-                ('A'..='Z', _) => return Err(PlugboardError::InvalidCharacter((left, right))),
-                (_, 'A'..='Z') => return Err(PlugboardError::InvalidCharacter((left, right))),
-                (_, _) => return Err(PlugboardError::InvalidCharacter((left, right))),
-            };
-
-            if intermediate.contains_key(&left) {
-                return Err(PlugboardError::CharacterAlreadyWired(left));
+            if left.is_ascii_uppercase() && right.is_ascii_uppercase() {
+                for letter in [left, right] {
+                    if board.is_steckered(letter) || self_paired & (1 << _index(letter)) != 0 {
+                        return Err(PlugboardError::CharacterAlreadyWired(letter));
+                    }
+                }
+
+                if left == right {
+                    self_paired |= 1 << _index(left);
+                    continue;
+                }
+            }
+
+            board.add_pair(left, right)?;
+        }
+
+        Ok(board)
+    }
+
+    /// Build a board from a letter-to-letter map. Every cable must appear in
+    /// both directions; letters that map to themselves are left unplugged.
+    #[cfg(feature = "std")]
+    pub fn from_map(intermediate: HashMap<char, char>) -> Result<Self, PlugboardError> {
+        let mut entries: Vec<(char, char)> = intermediate.into_iter().collect();
+        let mut board = Self::default();
+
+        entries.sort_unstable();
+
+        for (left, right) in entries.iter().copied() {
+            if !left.is_ascii_uppercase() || !right.is_ascii_uppercase() {
+                return Err(PlugboardError::InvalidCharacter((left, right)));
+            }
+
+            if left == right || board.transpose(left) == right {
+                continue;
             }
 
-            if intermediate.contains_key(&right) {
-                return Err(PlugboardError::CharacterAlreadyWired(right));
+            if entries.binary_search(&(right, left)).is_err() {
+                return Err(PlugboardError::NotReciprocal((left, right)));
             }
 
-            intermediate.insert(left, right);
-            intermediate.insert(right, left);
+            board.add_pair(left, right)?;
         }
 
-        Ok(Self(intermediate))
+        Ok(board)
     }
 
-    pub fn from_map(intermediate: HashMap<char, char>) -> Self {
-        Self(intermediate)
+    pub fn transpose(&self, input: char) -> char {
+        match input {
+            'A'..='Z' => (b'A' + self.0[_index(input)]) as char,
+            _ => input,
+        }
     }
 
-    pub fn transpose(&self, input: char) -> char {
-        match self.0.get(&input) {
-            Some(c) => *c,
-            None => input,
+    /// Plug in a single cable between `left` and `right`.
+    pub fn add_pair(&mut self, left: char, right: char) -> Result<(), PlugboardError> {
+        match (left, right) {
+            ('A'..='Z', 'A'..='Z') => {}
+            ('A'..='Z', _) => return Err(PlugboardError::InvalidCharacter((left, right))),
+            (_, 'A'..='Z') => return Err(PlugboardError::InvalidCharacter((left, right))),
+            (_, _) => return Err(PlugboardError::InvalidCharacter((left, right))),
+        };
+
+        if self.is_steckered(left) {
+            return Err(PlugboardError::CharacterAlreadyWired(left));
+        }
+
+        if self.is_steckered(right) {
+            return Err(PlugboardError::CharacterAlreadyWired(right));
+        }
+
+        if left == right {
+            return Err(PlugboardError::SelfWired(left));
+        }
+
+        self.0[_index(left)] = _index(right) as u8;
+        self.0[_index(right)] = _index(left) as u8;
+
+        Ok(())
+    }
+
+    /// Pull out the cable plugged into `letter`, returning the pair it joined.
+    pub fn remove_pair(&mut self, letter: char) -> Result<(char, char), PlugboardError> {
+        if !self.is_steckered(letter) {
+            return Err(PlugboardError::CharacterNotWired(letter));
+        }
+
+        let other = self.transpose(letter);
+        self.0[_index(letter)] = _index(letter) as u8;
+        self.0[_index(other)] = _index(other) as u8;
+
+        Ok((letter.min(other), letter.max(other)))
+    }
+
+    /// The cables on the board, each with its lower letter first, in
+    /// alphabetical order.
+    pub fn pairs(&self) -> impl Iterator<Item = (char, char)> + '_ {
+        ('A'..='Z')
+            .map(move |left| (left, self.transpose(left)))
+            .filter(|(left, right)| left < right)
+    }
+
+    pub fn cable_count(&self) -> usize {
+        self.pairs().count()
+    }
+
+    pub fn is_steckered(&self, letter: char) -> bool {
+        self.transpose(letter) != letter
+    }
+
+    pub fn permutation(&self) -> Permutation {
+        Permutation::from_fn(|c| self.transpose(c)).expect("plugboard wiring is a permutation")
+    }
+}
+
+impl Stecker for Plugboard {
+    fn transpose_in(&self, input: char) -> char {
+        self.transpose(input)
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        self.transpose(input)
+    }
+
+    fn permutation(&self) -> Permutation {
+        Plugboard::permutation(self)
+    }
+
+    /// Any reciprocal substitution can be plugged.
+    fn restore(&mut self, permutation: &Permutation) -> Result<(), SnapshotError> {
+        if !permutation.is_involution() {
+            return Err(SnapshotError::Stecker);
+        }
+
+        for (letter, slot) in ('A'..='Z').zip(self.0.iter_mut()) {
+            *slot = _index(permutation.apply(letter)) as u8;
+        }
+
+        Ok(())
+    }
+}
+
+/// Any fixed substitution can stand in for the stecker, such as the wired
+/// entry of a Typex.
+impl Stecker for Permutation {
+    fn transpose_in(&self, input: char) -> char {
+        self.apply(input)
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        self.inverse().apply(input)
+    }
+
+    fn permutation(&self) -> Permutation {
+        *self
+    }
+
+    fn restore(&mut self, permutation: &Permutation) -> Result<(), SnapshotError> {
+        *self = *permutation;
+
+        Ok(())
+    }
+}
+
+impl Default for Plugboard {
+    fn default() -> Self {
+        let mut table = [0u8; 26];
+
+        for (i, slot) in table.iter_mut().enumerate() {
+            *slot = i as u8;
         }
+
+        Self(table)
+    }
+}
+
+impl FromStr for Plugboard {
+    type Err = PlugboardError;
+
+    /// Parse the key-sheet notation, e.g. `"AV BS CG DL FU HZ IN KM OW RX"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut board = Self::default();
+
+        for (n, group) in s.split_whitespace().enumerate() {
+            let mut letters = group.chars();
+
+            match (letters.next(), letters.next(), letters.next()) {
+                (Some(left), Some(right), None) => board.add_pair(left, right)?,
+                _ => return Err(PlugboardError::MalformedPair(n + 1)),
+            }
+        }
+
+        Ok(board)
     }
 }
 
-#[macro_export]
-macro_rules! plugboard {
-    () => {{ None }};
-    ( $( $left:expr => $right:expr ),* ) => {
-        {
-            let mut intermediate = HashMap::new();
-            $(
-                match ($left, $right) {
-                    ('A'..='Z', 'A'..='Z') => {},
-                    ('A'..='Z', _)         => panic!("{:?} is not a valid character, must be A-Z", $right),
-                    (_, 'A'..='Z')         => panic!("{:?} is not a valid character, must be A-Z", $left),
-                    (_, _)                 => panic!("Neither {:?} nor {:?} are valid characters, must be A-Z", $left, $right),
-                };
-
-                assert!(!intermediate.contains_key(&$left), "{:?} already wired to plugboard!", $left);
-                assert!(!intermediate.contains_key(&$right), "{:?} already wired to plugboard!", $right);
-                intermediate.insert($left.clone(), $right.clone());
-                intermediate.insert($right.clone(), $left.clone());
-            )*
-
-            Some(Plugboard::from_map(intermediate))
+impl fmt::Display for Plugboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, (left, right)) in self.pairs().enumerate() {
+            if n > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{left}{right}")?;
         }
-    };
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::plugboard;
+    #[cfg(feature = "alloc")]
+    use alloc::{format, vec, vec::Vec};
 
     #[test]
     fn test_macro_one() {
-        let board = plugboard! { 'A' => 'E' }.unwrap();
+        let board = plugboard! { 'A' => 'E' };
 
         assert_eq!(board.transpose('A'), 'E');
         assert_eq!(board.transpose('E'), 'A');
@@ -117,8 +323,7 @@ mod test {
             'A' => 'E',
             'F' => 'J',
             'M' => 'G'
-        }
-        .unwrap();
+        };
 
         assert_eq!(board.transpose('A'), 'E');
         assert_eq!(board.transpose('E'), 'A');
@@ -129,69 +334,169 @@ mod test {
         assert_eq!(board.transpose('Z'), 'Z');
     }
 
+    #[test]
+    fn test_macro_compact() {
+        let board = plugboard!("AE FJ  MG");
+
+        assert_eq!(board, plugboard! { 'A' => 'E', 'F' => 'J', 'M' => 'G' });
+        assert_eq!(board.transpose('G'), 'M');
+        assert_eq!(plugboard!(""), plugboard! {});
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_parse_and_display() {
+        let board: Plugboard = "AV BS CG DL FU HZ IN KM OW RX".parse().unwrap();
+
+        assert_eq!(board.cable_count(), 10);
+        assert_eq!(board.transpose('V'), 'A');
+        assert_eq!(board.transpose('X'), 'R');
+        assert_eq!(format!("{board}"), "AV BS CG DL FU HZ IN KM OW RX");
+
+        let board: Plugboard = " ZA  yb".to_uppercase().parse().unwrap();
+        assert_eq!(
+            board.pairs().collect::<Vec<_>>(),
+            vec![('A', 'Z'), ('B', 'Y')]
+        );
+        assert_eq!(format!("{board}"), "AZ BY");
+
+        let board: Plugboard = "".parse().unwrap();
+        assert_eq!(board.cable_count(), 0);
+        assert_eq!(format!("{board}"), "");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "AV BSX".parse::<Plugboard>(),
+            Err(PlugboardError::MalformedPair(2))
+        );
+        assert_eq!(
+            "AV B".parse::<Plugboard>(),
+            Err(PlugboardError::MalformedPair(2))
+        );
+        assert_eq!(
+            "AV BA".parse::<Plugboard>(),
+            Err(PlugboardError::CharacterAlreadyWired('A'))
+        );
+        assert_eq!(
+            "AV b1".parse::<Plugboard>(),
+            Err(PlugboardError::InvalidCharacter(('b', '1')))
+        );
+        assert_eq!(
+            "AA".parse::<Plugboard>(),
+            Err(PlugboardError::SelfWired('A'))
+        );
+        // Plugboard::new has always taken a self-pair as no cable at all.
+        assert_eq!(Plugboard::new(&[('A', 'A')]), Ok(Plugboard::default()));
+        assert_eq!(
+            Plugboard::new(&[('A', 'A'), ('A', 'B')]),
+            Err(PlugboardError::CharacterAlreadyWired('A'))
+        );
+        assert_eq!(
+            Plugboard::default().add_pair('A', 'A'),
+            Err(PlugboardError::SelfWired('A'))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_add_and_remove_pair() {
+        let mut board = Plugboard::default();
+
+        board.add_pair('Q', 'C').unwrap();
+        board.add_pair('A', 'E').unwrap();
+
+        assert!(board.is_steckered('Q'));
+        assert!(board.is_steckered('C'));
+        assert!(!board.is_steckered('Z'));
+        assert_eq!(
+            board.pairs().collect::<Vec<_>>(),
+            vec![('A', 'E'), ('C', 'Q')]
+        );
+        assert_eq!(
+            board.add_pair('E', 'Z'),
+            Err(PlugboardError::CharacterAlreadyWired('E'))
+        );
+
+        assert_eq!(board.remove_pair('Q'), Ok(('C', 'Q')));
+        assert_eq!(board.transpose('C'), 'C');
+        assert_eq!(board.cable_count(), 1);
+        assert_eq!(
+            board.remove_pair('Q'),
+            Err(PlugboardError::CharacterNotWired('Q'))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_map() {
+        let map: HashMap<char, char> = [('A', 'E'), ('E', 'A'), ('Q', 'Q')].into_iter().collect();
+        let board = Plugboard::from_map(map).unwrap();
+
+        assert_eq!(board, plugboard! { 'A' => 'E' });
+
+        let map: HashMap<char, char> = [('A', 'E'), ('E', 'F')].into_iter().collect();
+        assert_eq!(
+            Plugboard::from_map(map),
+            Err(PlugboardError::NotReciprocal(('A', 'E')))
+        );
+
+        let map: HashMap<char, char> = [('A', 'e'), ('e', 'A')].into_iter().collect();
+        assert_eq!(
+            Plugboard::from_map(map),
+            Err(PlugboardError::InvalidCharacter(('A', 'e')))
+        );
+    }
+
+    #[test]
+    fn test_copy() {
+        let board = plugboard!("AE FJ");
+        let mut copy = board;
+
+        copy.remove_pair('A').unwrap();
+
+        assert_eq!(board.transpose('A'), 'E');
+        assert_eq!(copy.transpose('A'), 'A');
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_permutation() {
+        let board = Plugboard::new(&[('A', 'E'), ('F', 'J')]).unwrap();
+        let perm = board.permutation();
+
+        assert!(perm.is_involution());
+        assert_eq!(perm.apply('A'), 'E');
+        assert_eq!(perm.fixed_points().len(), 22);
+        assert_eq!(format!("{perm}"), "(AE)(FJ)");
+    }
+
     #[test]
     fn test_instantiation_errors() {
-        let board = Plugboard::new(&vec![('É', 'A')]);
+        let board = Plugboard::new(&[('É', 'A')]);
 
         assert!(board.is_err());
         assert_eq!(board, Err(PlugboardError::InvalidCharacter(('É', 'A'))));
 
-        let board = Plugboard::new(&vec![('A', 'É')]);
+        let board = Plugboard::new(&[('A', 'É')]);
 
         assert!(board.is_err());
         assert_eq!(board, Err(PlugboardError::InvalidCharacter(('A', 'É'))));
 
-        let board = Plugboard::new(&vec![('Ö', 'É')]);
+        let board = Plugboard::new(&[('Ö', 'É')]);
 
         assert!(board.is_err());
         assert_eq!(board, Err(PlugboardError::InvalidCharacter(('Ö', 'É'))));
 
-        let board = Plugboard::new(&vec![('A', 'F'), ('A', 'E')]);
+        let board = Plugboard::new(&[('A', 'F'), ('A', 'E')]);
 
         assert!(board.is_err());
         assert_eq!(board, Err(PlugboardError::CharacterAlreadyWired('A')));
 
-        let board = Plugboard::new(&vec![('A', 'F'), ('E', 'F')]);
+        let board = Plugboard::new(&[('A', 'F'), ('E', 'F')]);
 
         assert!(board.is_err());
         assert_eq!(board, Err(PlugboardError::CharacterAlreadyWired('F')));
     }
-
-    #[test]
-    #[should_panic(expected = "'É' is not a valid character, must be A-Z")]
-    fn test_macro_invalid_left() {
-        let _board = plugboard! { 'É' => 'A' };
-    }
-
-    #[test]
-    #[should_panic(expected = "'É' is not a valid character, must be A-Z")]
-    fn test_macro_invalid_right() {
-        let _board = plugboard! { 'A' => 'É' };
-    }
-
-    #[test]
-    #[should_panic(expected = "Neither 'Ö' nor 'É' are valid characters, must be A-Z")]
-    fn test_macro_both_invalid() {
-        let _board = plugboard! { 'Ö' => 'É' };
-    }
-
-    #[test]
-    #[allow(unreachable_patterns)]
-    #[should_panic(expected = "'A' already wired to plugboard!")]
-    fn test_macro_left_already_wired() {
-        let _board = plugboard! {
-            'A' => 'F',
-            'A' => 'E'
-        };
-    }
-
-    #[test]
-    #[allow(unreachable_patterns)]
-    #[should_panic(expected = "'F' already wired to plugboard!")]
-    fn test_macro_right_already_wired() {
-        let _board = plugboard! {
-            'A' => 'F',
-            'E' => 'F'
-        };
-    }
 }
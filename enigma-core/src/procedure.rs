@@ -0,0 +1,576 @@
+// src/procedure.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! Sending a long message the way operators did: split into parts of no more
+//! than 250 letters, each enciphered at its own message key, with the key
+//! passed to the receiver in the indicator of the part's header.
+
+use crate::message::{self, Message, MessageError, Part};
+use crate::{Enigma, EnigmaError};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+
+/// The most letters sent in one part.
+pub const PART_LETTERS: usize = 250;
+
+/// Why a multi-part message could not be sent or put back together. The
+/// procedure drives a machine, so a machine's [`EnigmaError`] is wrapped in
+/// here rather than the other way round.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ProcedureError {
+    Machine(EnigmaError),
+    Message(MessageError),
+    /// A part has no indicator to recover its message key from.
+    MissingIndicator(usize),
+    /// A part's indicator is the wrong length for the machine.
+    InvalidIndicator(usize),
+    /// The two copies of a doubled message key came out different.
+    GarbledIndicator(usize),
+    /// No part with this number was received.
+    MissingPart(usize),
+    /// A part turned up where another was expected.
+    OutOfOrder {
+        expected: usize,
+        found: usize,
+    },
+    /// The parts disagree on how many parts there are.
+    PartTotal {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Error for ProcedureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProcedureError::Machine(source) => Some(source),
+            ProcedureError::Message(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProcedureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcedureError::Machine(_) => write!(f, "the machine reported an error"),
+            ProcedureError::Message(_) => write!(f, "invalid message"),
+            ProcedureError::MissingIndicator(part) => {
+                write!(f, "part {part} has no indicator")
+            }
+            ProcedureError::InvalidIndicator(part) => {
+                write!(f, "the indicator of part {part} does not fit the machine")
+            }
+            ProcedureError::GarbledIndicator(part) => {
+                write!(f, "the message key of part {part} was garbled")
+            }
+            ProcedureError::MissingPart(part) => write!(f, "part {part} is missing"),
+            ProcedureError::OutOfOrder { expected, found } => {
+                write!(f, "expected part {expected}, found part {found}")
+            }
+            ProcedureError::PartTotal { expected, found } => {
+                write!(f, "expected {expected} parts in all, a part gives {found}")
+            }
+        }
+    }
+}
+
+impl From<EnigmaError> for ProcedureError {
+    fn from(source: EnigmaError) -> Self {
+        ProcedureError::Machine(source)
+    }
+}
+
+impl From<MessageError> for ProcedureError {
+    fn from(source: MessageError) -> Self {
+        ProcedureError::Message(source)
+    }
+}
+
+/// Somewhere to draw message keys from, one letter at a time.
+pub trait KeySource {
+    /// A letter `A`-`Z`.
+    fn next_letter(&mut self) -> char;
+}
+
+impl<F: FnMut() -> char> KeySource for F {
+    fn next_letter(&mut self) -> char {
+        self()
+    }
+}
+
+/// Letters from a small xorshift generator. Good enough to pick message
+/// keys, which the machine's own security never rested on.
+#[derive(Clone, Debug)]
+pub struct RandomKeys {
+    state: u64,
+}
+
+impl RandomKeys {
+    /// Draw the same letters every time for the same seed.
+    pub fn seeded(seed: u64) -> Self {
+        RandomKeys {
+            // xorshift never leaves zero.
+            state: seed.max(1),
+        }
+    }
+
+    /// Seed from the randomness the standard library gives each hasher.
+    #[cfg(feature = "std")]
+    pub fn new() -> Self {
+        use std::hash::{BuildHasher, Hasher};
+
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(0);
+        RandomKeys::seeded(hasher.finish())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for RandomKeys {
+    fn default() -> Self {
+        RandomKeys::new()
+    }
+}
+
+impl KeySource for RandomKeys {
+    fn next_letter(&mut self) -> char {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        let mixed = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32;
+
+        (b'A' + (mixed % 26) as u8) as char
+    }
+}
+
+/// How the message key of each part reaches the receiver.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Indicator {
+    /// Until 1940: the message key is typed twice at the ground setting
+    /// from the key sheet, and the doubled result sent as the indicator.
+    Doubled { ground: Vec<char> },
+    /// From 1940: the operator picks a ground setting for each part, sends
+    /// it in the clear and follows it with the message key typed once at it.
+    Chosen,
+}
+
+/// Splits, enciphers and reassembles multi-part messages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Procedure {
+    indicator: Indicator,
+    part_letters: usize,
+}
+
+impl Procedure {
+    pub fn new(indicator: Indicator) -> Self {
+        Procedure {
+            indicator,
+            part_letters: PART_LETTERS,
+        }
+    }
+
+    /// Send no more than `part_letters` letters in one part.
+    pub fn part_letters(mut self, part_letters: usize) -> Self {
+        self.part_letters = part_letters.max(1);
+        self
+    }
+
+    /// Encipher the letters of `plaintext` as evenly sized parts, each at a
+    /// fresh message key from `keys`. The machine is left as it was found.
+    pub fn encipher<M: Enigma + ?Sized>(
+        &self,
+        machine: &mut M,
+        plaintext: &str,
+        keys: &mut impl KeySource,
+    ) -> Result<Vec<Message>, ProcedureError> {
+        let snapshot = machine.snapshot();
+        let result = self._encipher(machine, plaintext, keys);
+        machine.restore(&snapshot)?;
+
+        result
+    }
+
+    /// Read each part's message key from its indicator and put the
+    /// plaintext back together, checking no part is missing or out of
+    /// order. The machine is left as it was found.
+    pub fn decipher<M: Enigma + ?Sized>(
+        &self,
+        machine: &mut M,
+        parts: &[Message],
+    ) -> Result<String, ProcedureError> {
+        _check_parts(parts)?;
+
+        let snapshot = machine.snapshot();
+        let result = self._decipher(machine, parts);
+        machine.restore(&snapshot)?;
+
+        result
+    }
+
+    fn _encipher<M: Enigma + ?Sized>(
+        &self,
+        machine: &mut M,
+        plaintext: &str,
+        keys: &mut impl KeySource,
+    ) -> Result<Vec<Message>, ProcedureError> {
+        let letters = message::raw(plaintext);
+        let total = letters.len().div_ceil(self.part_letters).max(1);
+        let size = letters.len().div_ceil(total).max(1);
+        let key_length = machine.settings().len();
+
+        let mut parts = Vec::with_capacity(total);
+        for number in 1..=total {
+            let start = ((number - 1) * size).min(letters.len());
+            let end = (number * size).min(letters.len());
+
+            let mut draw =
+                || -> Vec<char> { (0..key_length).map(|_| keys.next_letter()).collect() };
+            let (ground, key) = match &self.indicator {
+                Indicator::Doubled { ground } => (ground.clone(), draw()),
+                Indicator::Chosen => (draw(), draw()),
+            };
+
+            let typed: String = key.iter().collect();
+            let indicator = match &self.indicator {
+                Indicator::Doubled { .. } => {
+                    _encipher_at(machine, &ground, &[typed.as_str(), &typed].concat())?
+                }
+                Indicator::Chosen => {
+                    let enciphered = _encipher_at(machine, &ground, &typed)?;
+                    [ground.iter().collect(), enciphered].concat()
+                }
+            };
+            let ciphertext = _encipher_at(machine, &key, &letters[start..end])?;
+
+            parts.push(
+                Message::new(&ciphertext)
+                    .part(number, total)
+                    .indicator(&_groups(&indicator, key_length)),
+            );
+        }
+
+        Ok(parts)
+    }
+
+    fn _decipher<M: Enigma + ?Sized>(
+        &self,
+        machine: &mut M,
+        parts: &[Message],
+    ) -> Result<String, ProcedureError> {
+        let key_length = machine.settings().len();
+        let mut plaintext = String::new();
+
+        for (n, part) in parts.iter().enumerate() {
+            let number = n + 1;
+            let indicator = message::raw(
+                part.header
+                    .indicator
+                    .as_deref()
+                    .ok_or(ProcedureError::MissingIndicator(number))?,
+            );
+            if indicator.len() != 2 * key_length {
+                return Err(ProcedureError::InvalidIndicator(number));
+            }
+
+            let key = match &self.indicator {
+                Indicator::Doubled { ground } => {
+                    let doubled = _encipher_at(machine, ground, &indicator)?;
+                    let (key, again) = doubled.split_at(key_length);
+                    if key != again {
+                        return Err(ProcedureError::GarbledIndicator(number));
+                    }
+                    String::from(key)
+                }
+                Indicator::Chosen => {
+                    let (ground, enciphered) = indicator.split_at(key_length);
+                    let ground: Vec<char> = ground.chars().collect();
+                    _encipher_at(machine, &ground, enciphered)?
+                }
+            };
+
+            if part.text.len() != part.header.letters {
+                return Err(MessageError::LetterCount {
+                    expected: part.header.letters,
+                    found: part.text.len(),
+                }
+                .into());
+            }
+
+            let key: Vec<char> = key.chars().collect();
+            plaintext.push_str(&_encipher_at(machine, &key, &part.text)?);
+        }
+
+        Ok(plaintext)
+    }
+}
+
+/// Every part from the first to the last, once each and in order. A lone
+/// message with no part in its header counts as the whole of it.
+fn _check_parts(parts: &[Message]) -> Result<(), ProcedureError> {
+    let whole = Part {
+        number: 1,
+        total: 1,
+    };
+    let part_of = |message: &Message| message.header.part.unwrap_or(whole);
+    let total = parts.first().map_or(1, |message| part_of(message).total);
+
+    for (n, message) in parts.iter().enumerate() {
+        let part = part_of(message);
+
+        if part.total != total {
+            return Err(ProcedureError::PartTotal {
+                expected: total,
+                found: part.total,
+            });
+        }
+
+        if part.number != n + 1 {
+            return match parts.iter().any(|message| part_of(message).number == n + 1) {
+                true => Err(ProcedureError::OutOfOrder {
+                    expected: n + 1,
+                    found: part.number,
+                }),
+                false => Err(ProcedureError::MissingPart(n + 1)),
+            };
+        }
+    }
+
+    match parts.len() < total {
+        true => Err(ProcedureError::MissingPart(parts.len() + 1)),
+        false => Ok(()),
+    }
+}
+
+/// Turn the machine to `positions` and type `text`.
+fn _encipher_at<M: Enigma + ?Sized>(
+    machine: &mut M,
+    positions: &[char],
+    text: &str,
+) -> Result<String, EnigmaError> {
+    machine.set_positions(positions)?;
+    machine.encipher_str(text)
+}
+
+fn _groups(letters: &str, size: usize) -> String {
+    message::Layout::new().group_size(size).format(letters)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reflectors::ReflectorB;
+    use crate::rotors::*;
+    use crate::{plugboard, ArmyEnigma};
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn machine() -> impl Enigma {
+        ArmyEnigma::new(
+            RotorII::new('B', 'A'),
+            RotorIV::new('U', 'A'),
+            RotorV::new('L', 'A'),
+            ReflectorB {},
+            plugboard! {
+                'A' => 'V', 'B' => 'S', 'C' => 'G', 'D' => 'L', 'F' => 'U',
+                'H' => 'Z', 'I' => 'N', 'K' => 'M', 'O' => 'W', 'R' => 'X',
+            },
+        )
+    }
+
+    fn plaintext(letters: usize) -> String {
+        "DERFUEHRERISTTOTDERKAMPFGEHTWEITER"
+            .chars()
+            .cycle()
+            .take(letters)
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = plaintext(600);
+
+        for indicator in [
+            Indicator::Chosen,
+            Indicator::Doubled {
+                ground: vec!['W', 'Z', 'A'],
+            },
+        ] {
+            let procedure = Procedure::new(indicator);
+            let mut sender = machine();
+            let parts = procedure
+                .encipher(&mut sender, &text, &mut RandomKeys::seeded(1940))
+                .unwrap();
+
+            assert_eq!(parts.len(), 3);
+            for (n, part) in parts.iter().enumerate() {
+                assert_eq!(part.header.letters, 200);
+                assert_eq!(part.header.indicator.as_ref().unwrap().len(), 7);
+                assert_eq!(
+                    part.header.part,
+                    Some(Part {
+                        number: n + 1,
+                        total: 3
+                    })
+                );
+            }
+            assert_ne!(parts[0].header.indicator, parts[1].header.indicator);
+            assert_eq!(sender.settings(), vec!['A', 'A', 'A']);
+
+            // Through the header lines and back, as over the air.
+            let received: Vec<Message> = parts
+                .iter()
+                .map(|part| part.to_string().parse().unwrap())
+                .collect();
+            assert_eq!(
+                procedure.decipher(&mut machine(), &received),
+                Ok(text.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_part_sizes() {
+        let procedure = Procedure::new(Indicator::Chosen).part_letters(10);
+        let mut keys = RandomKeys::seeded(7);
+        let sizes = |text: &str, keys: &mut RandomKeys| -> Vec<usize> {
+            procedure
+                .encipher(&mut machine(), text, keys)
+                .unwrap()
+                .iter()
+                .map(|part| part.header.letters)
+                .collect()
+        };
+
+        assert_eq!(sizes(&plaintext(10), &mut keys), vec![10]);
+        assert_eq!(sizes(&plaintext(11), &mut keys), vec![6, 5]);
+        assert_eq!(sizes(&plaintext(25), &mut keys), vec![9, 9, 7]);
+        assert_eq!(sizes("", &mut keys), vec![0]);
+    }
+
+    #[test]
+    fn test_known_key() {
+        // With the ground setting and message key fixed, the indicator is
+        // the key typed at the ground setting and the text is typed at the
+        // key.
+        let mut letters = "WZAUHL".chars();
+        let parts = Procedure::new(Indicator::Chosen)
+            .encipher(&mut machine(), "enigma", &mut || letters.next().unwrap())
+            .unwrap();
+
+        let mut check = machine();
+        check.set_positions(&['W', 'Z', 'A']).unwrap();
+        let indicator = ["WZA ", &check.encipher_str("UHL").unwrap()].concat();
+        check.set_positions(&['U', 'H', 'L']).unwrap();
+
+        assert_eq!(parts[0].header.indicator, Some(indicator));
+        assert_eq!(parts[0].text, check.encipher_str("ENIGMA").unwrap());
+    }
+
+    #[test]
+    fn test_random_keys() {
+        let mut keys = RandomKeys::seeded(1);
+        let mut counts = [0usize; 26];
+
+        for _ in 0..260_000 {
+            counts[(keys.next_letter() as u8 - b'A') as usize] += 1;
+        }
+
+        // Every letter turns up about ten thousand times. Reducing the draw to
+        // a byte before taking it mod 26 would leave W-Z a tenth short.
+        assert!(counts.iter().all(|count| (9_700..10_300).contains(count)));
+    }
+
+    #[test]
+    fn test_missing_parts() {
+        let procedure = Procedure::new(Indicator::Chosen).part_letters(10);
+        let parts = procedure
+            .encipher(&mut machine(), &plaintext(40), &mut RandomKeys::seeded(3))
+            .unwrap();
+        let decipher = |parts: &[Message]| procedure.decipher(&mut machine(), parts);
+
+        assert_eq!(
+            decipher(&[parts[0].clone(), parts[2].clone(), parts[3].clone()]),
+            Err(ProcedureError::MissingPart(2))
+        );
+        assert_eq!(decipher(&parts[..3]), Err(ProcedureError::MissingPart(4)));
+        assert_eq!(
+            decipher(&[parts[1].clone(), parts[0].clone()]),
+            Err(ProcedureError::OutOfOrder {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(decipher(&[]), Err(ProcedureError::MissingPart(1)));
+
+        let mut stray = parts[1].clone();
+        stray.header.part = Some(Part {
+            number: 2,
+            total: 5,
+        });
+        assert_eq!(
+            decipher(&[parts[0].clone(), stray]),
+            Err(ProcedureError::PartTotal {
+                expected: 4,
+                found: 5
+            })
+        );
+    }
+
+    #[test]
+    fn test_indicator_errors() {
+        let ground = vec!['W', 'Z', 'A'];
+        let procedure = Procedure::new(Indicator::Doubled {
+            ground: ground.clone(),
+        });
+        let parts = procedure
+            .encipher(&mut machine(), "ENIGMA", &mut RandomKeys::seeded(5))
+            .unwrap();
+
+        // Deciphered at the wrong ground setting, the two copies of the key
+        // no longer agree.
+        let wrong = Procedure::new(Indicator::Doubled {
+            ground: vec!['A', 'A', 'A'],
+        });
+        assert_eq!(
+            wrong.decipher(&mut machine(), &parts),
+            Err(ProcedureError::GarbledIndicator(1))
+        );
+
+        let mut part = parts[0].clone();
+        part.header.indicator = Some("ABC".into());
+        assert_eq!(
+            procedure.decipher(&mut machine(), &[part.clone()]),
+            Err(ProcedureError::InvalidIndicator(1))
+        );
+
+        part.header.indicator = None;
+        assert_eq!(
+            procedure.decipher(&mut machine(), &[part]),
+            Err(ProcedureError::MissingIndicator(1))
+        );
+
+        assert_eq!(
+            procedure.encipher(&mut machine(), "ENIGMA", &mut || '1'),
+            Err(ProcedureError::Machine(EnigmaError::Rotor {
+                slot: 1,
+                source: RotorError::InvalidPosition('1')
+            }))
+        );
+        assert_eq!(
+            ProcedureError::Machine(EnigmaError::NoSuchSlot(4)).to_string(),
+            "the machine reported an error"
+        );
+    }
+}
@@ -7,20 +7,132 @@
 // file may not be copied, modified, or distributed except according to those
 // terms.
 
+use crate::permutation::Permutation;
 use enigma_macros::Reflector;
 
+use core::error::Error;
+use core::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum ReflectorError {
+    InvalidPosition(char),
+}
+
+impl Error for ReflectorError {}
+impl fmt::Display for ReflectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectorError::InvalidPosition(c) => {
+                write!(f, "'{c}' is not a valid reflector position, must be A-Z")
+            }
+        }
+    }
+}
+
 pub trait Reflector {
     fn transpose(&self, input: char) -> char;
+
+    fn permutation(&self) -> Permutation {
+        Permutation::from_fn(|c| self.transpose(c)).expect("reflector wiring is a permutation")
+    }
+
+    /// The name the reflector goes by in key notation, such as `B`. Machines
+    /// with a single reflector just call it the `UKW`.
+    fn name(&self) -> &'static str {
+        "UKW"
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<R: Reflector + ?Sized> Reflector for alloc::boxed::Box<R> {
+    fn transpose(&self, input: char) -> char {
+        (**self).transpose(input)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
+/// A reflector that can be turned by hand to any of 26 positions, as on the
+/// commercial machines. It is set with the key and does not step while typing.
+pub trait SettableReflector: Reflector {
+    fn position(&self) -> char;
+    fn set_position(&mut self, position: char) -> Result<(), ReflectorError>;
+    /// Turn the reflector on by one position, as the gearing of the G does.
+    fn advance(&mut self);
+}
+
+/// Define a [`SettableReflector`] from its wiring at position `A`.
+macro_rules! settable_reflector {
+    ($(#[$attr:meta])* $name:ident, $wiring:literal) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        pub struct $name {
+            offset: u8,
+        }
+
+        impl $name {
+            const WIRING: &'static [u8; 26] = $wiring;
+
+            pub fn new(position: char) -> Result<Self, $crate::reflectors::ReflectorError> {
+                let mut reflector = Self::default();
+                $crate::reflectors::SettableReflector::set_position(&mut reflector, position)?;
+
+                Ok(reflector)
+            }
+        }
+
+        impl $crate::reflectors::Reflector for $name {
+            fn transpose(&self, input: char) -> char {
+                let contact = (input as u8 - b'A' + self.offset) % 26;
+                let output = (Self::WIRING[contact as usize] - b'A' + 26 - self.offset) % 26;
+
+                (b'A' + output) as char
+            }
+        }
+
+        impl $crate::reflectors::SettableReflector for $name {
+            fn position(&self) -> char {
+                (b'A' + self.offset) as char
+            }
+
+            fn set_position(
+                &mut self,
+                position: char,
+            ) -> Result<(), $crate::reflectors::ReflectorError> {
+                if !position.is_ascii_uppercase() {
+                    return Err($crate::reflectors::ReflectorError::InvalidPosition(position));
+                }
+
+                self.offset = position as u8 - b'A';
+
+                Ok(())
+            }
+
+            fn advance(&mut self) {
+                self.offset = (self.offset + 1) % 26;
+            }
+        }
+    };
 }
 
-#[derive(Reflector)]
+pub(crate) use settable_reflector;
+
+#[derive(Default, Reflector)]
 #[key_ordering(EJMZALYXVBWFCRQUONTSPIKHGD)]
 pub struct ReflectorA;
 
-#[derive(Reflector)]
+#[derive(Default, Reflector)]
 #[key_ordering(YRUHQSLDPXNGOKMIEBFZCWVJAT)]
 pub struct ReflectorB;
 
-#[derive(Reflector)]
+#[derive(Default, Reflector)]
 #[key_ordering(FVPJIAOYEDRZXWGCTKUQSBNMHL)]
 pub struct ReflectorC;
+
+/// The thin reflector of the naval M4, used alongside the Beta wheel.
+#[derive(Default, Reflector)]
+#[key_ordering(ENKQAUYWJICOPBLMDXZVFTHRGS)]
+#[name("B-thin")]
+pub struct ReflectorBThin;
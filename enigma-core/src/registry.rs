@@ -0,0 +1,460 @@
+// src/registry.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::abwehr::*;
+use crate::commercial::*;
+use crate::model::Model;
+use crate::reflectors::*;
+use crate::rotors::*;
+
+use alloc::{borrow::ToOwned, boxed::Box, string::String, vec::Vec};
+use core::error::Error;
+use core::fmt;
+
+/// Why a wheel could not be looked up or registered. A lookup happens before
+/// there is a machine, so these are not [`EnigmaError`](crate::EnigmaError)s.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum RegistryError {
+    UnknownRotor(Model, String),
+    UnknownReflector(Model, String),
+    DuplicateName(Model, String),
+    Rotor(RotorError),
+    Reflector(ReflectorError),
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RegistryError::Rotor(source) => Some(source),
+            RegistryError::Reflector(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::UnknownRotor(model, name) => {
+                write!(f, "the {model} has no rotor named '{name}'")
+            }
+            RegistryError::UnknownReflector(model, name) => {
+                write!(f, "the {model} has no reflector named '{name}'")
+            }
+            RegistryError::DuplicateName(model, name) => {
+                write!(f, "the {model} already has a wheel named '{name}'")
+            }
+            RegistryError::Rotor(_) => write!(f, "invalid rotor setting"),
+            RegistryError::Reflector(_) => write!(f, "invalid reflector setting"),
+        }
+    }
+}
+
+impl From<RotorError> for RegistryError {
+    fn from(source: RotorError) -> Self {
+        RegistryError::Rotor(source)
+    }
+}
+
+impl From<ReflectorError> for RegistryError {
+    fn from(source: ReflectorError) -> Self {
+        RegistryError::Reflector(source)
+    }
+}
+
+fn _build_rotor<R: RotorEncode + 'static>(
+    ring_setting: char,
+    position: char,
+) -> Result<Box<dyn RotorEncode>, RotorError> {
+    Ok(Box::new(R::try_new(ring_setting, position)?))
+}
+
+fn _build_fixed<R: Reflector + Default + 'static>(
+    position: char,
+) -> Result<Box<dyn Reflector>, ReflectorError> {
+    match position {
+        'A' => Ok(Box::new(R::default())),
+        c => Err(ReflectorError::InvalidPosition(c)),
+    }
+}
+
+fn _build_settable<R: SettableReflector + Default + 'static>(
+    position: char,
+) -> Result<Box<dyn Reflector>, ReflectorError> {
+    let mut reflector = R::default();
+    reflector.set_position(position)?;
+
+    Ok(Box::new(reflector))
+}
+
+/// A rotor that can be built by name.
+#[derive(Clone, Copy)]
+pub struct RotorSpec {
+    pub model: Model,
+    pub name: &'static str,
+    build: fn(char, char) -> Result<Box<dyn RotorEncode>, RotorError>,
+}
+
+impl RotorSpec {
+    pub fn new<R: RotorEncode + 'static>(model: Model, name: &'static str) -> Self {
+        RotorSpec {
+            model,
+            name,
+            build: _build_rotor::<R>,
+        }
+    }
+
+    pub fn build(
+        &self,
+        ring_setting: char,
+        position: char,
+    ) -> Result<Box<dyn RotorEncode>, RotorError> {
+        (self.build)(ring_setting, position)
+    }
+
+    /// The wiring at ring setting and position `A`.
+    pub fn wiring(&self) -> String {
+        self.build('A', 'A').unwrap().permutation().wiring()
+    }
+
+    /// The window letters from which the rotor carries into its neighbour.
+    pub fn notches(&self) -> String {
+        ('A'..='Z')
+            .filter(|c| self.build('A', *c).unwrap().at_notch())
+            .collect()
+    }
+}
+
+/// A reflector that can be built by name. Reflectors that cannot be set only
+/// accept position `A`.
+#[derive(Clone, Copy)]
+pub struct ReflectorSpec {
+    pub model: Model,
+    pub name: &'static str,
+    pub settable: bool,
+    build: fn(char) -> Result<Box<dyn Reflector>, ReflectorError>,
+}
+
+impl ReflectorSpec {
+    pub fn fixed<R: Reflector + Default + 'static>(model: Model, name: &'static str) -> Self {
+        ReflectorSpec {
+            model,
+            name,
+            settable: false,
+            build: _build_fixed::<R>,
+        }
+    }
+
+    pub fn settable<R: SettableReflector + Default + 'static>(
+        model: Model,
+        name: &'static str,
+    ) -> Self {
+        ReflectorSpec {
+            model,
+            name,
+            settable: true,
+            build: _build_settable::<R>,
+        }
+    }
+
+    pub fn build(&self, position: char) -> Result<Box<dyn Reflector>, ReflectorError> {
+        (self.build)(position)
+    }
+
+    /// The wiring at position `A`.
+    pub fn wiring(&self) -> String {
+        self.build('A').unwrap().permutation().wiring()
+    }
+}
+
+/// Every rotor and reflector that can be looked up by its historical name,
+/// grouped by machine model. Names are matched ignoring case, and reflectors
+/// may be named with or without their `UKW-` prefix.
+#[derive(Clone, Default)]
+pub struct Registry {
+    rotors: Vec<RotorSpec>,
+    reflectors: Vec<ReflectorSpec>,
+}
+
+fn _same_name(name: &str, query: &str) -> bool {
+    name.eq_ignore_ascii_case(query.trim())
+}
+
+/// Reflector names match without their `UKW-` prefix, and with spaces and
+/// hyphens alike, so `B thin` finds `UKW-B-thin`.
+fn _same_reflector(name: &str, query: &str) -> bool {
+    let strip = |s: &str| {
+        let s = s.trim().replace(' ', "-");
+        match s.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("UKW-") => s[4..].to_owned(),
+            _ => s,
+        }
+    };
+
+    strip(name).eq_ignore_ascii_case(&strip(query))
+}
+
+impl Registry {
+    /// A registry of every wheel in this crate.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+
+        for model in [Model::EnigmaI, Model::M4] {
+            registry.rotors.extend([
+                RotorSpec::new::<RotorI>(model, "I"),
+                RotorSpec::new::<RotorII>(model, "II"),
+                RotorSpec::new::<RotorIII>(model, "III"),
+                RotorSpec::new::<RotorIV>(model, "IV"),
+                RotorSpec::new::<RotorV>(model, "V"),
+                RotorSpec::new::<RotorVI>(model, "VI"),
+                RotorSpec::new::<RotorVII>(model, "VII"),
+                RotorSpec::new::<RotorVIII>(model, "VIII"),
+            ]);
+        }
+        registry
+            .rotors
+            .push(RotorSpec::new::<RotorBeta>(Model::M4, "Beta"));
+        registry.reflectors.extend([
+            ReflectorSpec::fixed::<ReflectorA>(Model::EnigmaI, "UKW-A"),
+            ReflectorSpec::fixed::<ReflectorB>(Model::EnigmaI, "UKW-B"),
+            ReflectorSpec::fixed::<ReflectorC>(Model::EnigmaI, "UKW-C"),
+            ReflectorSpec::fixed::<ReflectorBThin>(Model::M4, "UKW-B-thin"),
+        ]);
+
+        for model in [Model::EnigmaD, Model::EnigmaK] {
+            registry.rotors.extend([
+                RotorSpec::new::<RotorKI>(model, "I"),
+                RotorSpec::new::<RotorKII>(model, "II"),
+                RotorSpec::new::<RotorKIII>(model, "III"),
+            ]);
+            registry
+                .reflectors
+                .push(ReflectorSpec::settable::<ReflectorK>(model, "UKW"));
+        }
+
+        registry.rotors.extend([
+            RotorSpec::new::<RotorSwissKI>(Model::SwissK, "I"),
+            RotorSpec::new::<RotorSwissKII>(Model::SwissK, "II"),
+            RotorSpec::new::<RotorSwissKIII>(Model::SwissK, "III"),
+            RotorSpec::new::<RotorRailwayI>(Model::Railway, "I"),
+            RotorSpec::new::<RotorRailwayII>(Model::Railway, "II"),
+            RotorSpec::new::<RotorRailwayIII>(Model::Railway, "III"),
+            RotorSpec::new::<RotorTI>(Model::Tirpitz, "I"),
+            RotorSpec::new::<RotorTII>(Model::Tirpitz, "II"),
+            RotorSpec::new::<RotorTIII>(Model::Tirpitz, "III"),
+            RotorSpec::new::<RotorTIV>(Model::Tirpitz, "IV"),
+            RotorSpec::new::<RotorTV>(Model::Tirpitz, "V"),
+            RotorSpec::new::<RotorTVI>(Model::Tirpitz, "VI"),
+            RotorSpec::new::<RotorTVII>(Model::Tirpitz, "VII"),
+            RotorSpec::new::<RotorTVIII>(Model::Tirpitz, "VIII"),
+            RotorSpec::new::<RotorG312I>(Model::G312, "I"),
+            RotorSpec::new::<RotorG312II>(Model::G312, "II"),
+            RotorSpec::new::<RotorG312III>(Model::G312, "III"),
+            RotorSpec::new::<RotorG260I>(Model::G260, "I"),
+            RotorSpec::new::<RotorG260II>(Model::G260, "II"),
+            RotorSpec::new::<RotorG260III>(Model::G260, "III"),
+        ]);
+        registry.reflectors.extend([
+            ReflectorSpec::settable::<ReflectorK>(Model::SwissK, "UKW"),
+            ReflectorSpec::settable::<ReflectorRailway>(Model::Railway, "UKW"),
+            ReflectorSpec::settable::<ReflectorT>(Model::Tirpitz, "UKW"),
+            ReflectorSpec::settable::<ReflectorG312>(Model::G312, "UKW"),
+            ReflectorSpec::settable::<ReflectorG260>(Model::G260, "UKW"),
+        ]);
+
+        registry
+    }
+
+    /// Add a rotor, such as one defined with [`crate::rotor!`] in another
+    /// crate. Its name must be new for its model.
+    pub fn register_rotor(&mut self, spec: RotorSpec) -> Result<(), RegistryError> {
+        if self.rotor_spec(spec.model, spec.name).is_some() {
+            return Err(RegistryError::DuplicateName(spec.model, spec.name.into()));
+        }
+
+        self.rotors.push(spec);
+
+        Ok(())
+    }
+
+    pub fn register_reflector(&mut self, spec: ReflectorSpec) -> Result<(), RegistryError> {
+        if self.reflector_spec(spec.model, spec.name).is_some() {
+            return Err(RegistryError::DuplicateName(spec.model, spec.name.into()));
+        }
+
+        self.reflectors.push(spec);
+
+        Ok(())
+    }
+
+    pub fn rotors(&self, model: Model) -> impl Iterator<Item = &RotorSpec> {
+        self.rotors.iter().filter(move |spec| spec.model == model)
+    }
+
+    pub fn reflectors(&self, model: Model) -> impl Iterator<Item = &ReflectorSpec> {
+        self.reflectors
+            .iter()
+            .filter(move |spec| spec.model == model)
+    }
+
+    pub fn rotor_spec(&self, model: Model, name: &str) -> Option<&RotorSpec> {
+        self.rotors(model).find(|spec| _same_name(spec.name, name))
+    }
+
+    pub fn reflector_spec(&self, model: Model, name: &str) -> Option<&ReflectorSpec> {
+        self.reflectors(model)
+            .find(|spec| _same_reflector(spec.name, name))
+    }
+
+    pub fn rotor(
+        &self,
+        model: Model,
+        name: &str,
+        ring_setting: char,
+        position: char,
+    ) -> Result<Box<dyn RotorEncode>, RegistryError> {
+        let spec = self
+            .rotor_spec(model, name)
+            .ok_or_else(|| RegistryError::UnknownRotor(model, name.into()))?;
+
+        Ok(spec.build(ring_setting, position)?)
+    }
+
+    pub fn reflector(
+        &self,
+        model: Model,
+        name: &str,
+        position: char,
+    ) -> Result<Box<dyn Reflector>, RegistryError> {
+        let spec = self
+            .reflector_spec(model, name)
+            .ok_or_else(|| RegistryError::UnknownReflector(model, name.into()))?;
+
+        Ok(spec.build(position)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::machine::{RotorMachine, Slot};
+    use crate::stepping::Pawl;
+    use crate::Enigma;
+
+    use alloc::format;
+    use alloc::vec;
+
+    #[test]
+    fn test_lookup() {
+        let registry = Registry::builtin();
+        let spec = registry.rotor_spec(Model::EnigmaI, "iii").unwrap();
+
+        assert_eq!(spec.name, "III");
+        assert_eq!(spec.wiring(), "BDFHJLCPRTXVZNYEIWGAKMUSQO");
+        assert_eq!(spec.notches(), "V");
+        assert_eq!(
+            registry.rotor_spec(Model::Tirpitz, "VI").unwrap().notches(),
+            "EIMQX"
+        );
+        assert_eq!(
+            registry
+                .rotor_spec(Model::G312, "III")
+                .unwrap()
+                .notches()
+                .len(),
+            11
+        );
+
+        let spec = registry.reflector_spec(Model::EnigmaI, "B").unwrap();
+        assert_eq!(spec.name, "UKW-B");
+        assert_eq!(spec.wiring(), "YRUHQSLDPXNGOKMIEBFZCWVJAT");
+        assert!(registry.reflector_spec(Model::M4, "ukw-b THIN").is_some());
+    }
+
+    #[test]
+    fn test_every_model() {
+        let registry = Registry::builtin();
+
+        for model in Model::ALL {
+            assert!(registry.rotors(model).count() >= 3, "{model}");
+            assert!(registry.reflectors(model).count() >= 1, "{model}");
+        }
+    }
+
+    #[test]
+    fn test_wheel_names() {
+        let registry = Registry::builtin();
+
+        for model in Model::ALL {
+            for spec in registry.rotors(model) {
+                assert_eq!(spec.build('A', 'A').unwrap().name(), spec.name);
+            }
+
+            for spec in registry.reflectors(model) {
+                let name = spec.build('A').unwrap().name();
+                assert!(_same_reflector(spec.name, name), "{model} {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_by_name() {
+        let registry = Registry::builtin();
+        let slots = ["I", "II", "III"]
+            .iter()
+            .map(|name| registry.rotor(Model::EnigmaI, name, 'A', 'A'))
+            .map(|rotor| Slot::boxed(rotor.unwrap(), true))
+            .collect();
+        let reflector = registry.reflector(Model::EnigmaI, "UKW-B", 'A').unwrap();
+        let mut machine = RotorMachine::new(slots, reflector, Pawl, None);
+
+        assert_eq!(machine.encipher_str("AAAAA").unwrap(), "BDZGO");
+        assert_eq!(machine.settings(), vec!['A', 'A', 'F']);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut registry = Registry::builtin();
+
+        assert_eq!(
+            registry.rotor(Model::SwissK, "IV", 'A', 'A').err(),
+            Some(RegistryError::UnknownRotor(Model::SwissK, "IV".into()))
+        );
+        assert_eq!(
+            registry.reflector(Model::Railway, "UKW-B", 'A').err(),
+            Some(RegistryError::UnknownReflector(
+                Model::Railway,
+                "UKW-B".into()
+            ))
+        );
+        assert_eq!(
+            registry.rotor(Model::EnigmaI, "I", 'a', 'A').err(),
+            Some(RegistryError::Rotor(RotorError::InvalidRingSetting('a')))
+        );
+        assert_eq!(
+            registry.reflector(Model::EnigmaI, "C", 'F').err(),
+            Some(RegistryError::Reflector(ReflectorError::InvalidPosition(
+                'F'
+            )))
+        );
+        assert!(registry.reflector(Model::Tirpitz, "UKW", 'F').is_ok());
+        assert_eq!(
+            registry.register_rotor(RotorSpec::new::<RotorV>(Model::EnigmaI, "v")),
+            Err(RegistryError::DuplicateName(Model::EnigmaI, "v".into()))
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                RegistryError::UnknownRotor(Model::SwissK, "IV".into())
+            ),
+            "the Swiss-K has no rotor named 'IV'"
+        );
+    }
+}
@@ -7,9 +7,38 @@
 // file may not be copied, modified, or distributed except according to those
 // terms.
 
+use crate::permutation::Permutation;
 use enigma_macros::RotorEncode;
 
-fn _apply_offset(shifted: i8) -> char {
+use core::error::Error;
+use core::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum RotorError {
+    InvalidRingSetting(char),
+    InvalidRingNumber(u8),
+    InvalidPosition(char),
+}
+
+impl Error for RotorError {}
+impl fmt::Display for RotorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotorError::InvalidRingSetting(c) => {
+                write!(f, "'{c}' is not a valid ring setting, must be A-Z")
+            }
+            RotorError::InvalidRingNumber(n) => {
+                write!(f, "{n:02} is not a valid ring setting, must be 01-26")
+            }
+            RotorError::InvalidPosition(c) => {
+                write!(f, "'{c}' is not a valid rotor position, must be A-Z")
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn _apply_offset(shifted: i8) -> char {
     if shifted > 90 {
         ((shifted - 26) as u8) as char
     } else if shifted < 65 {
@@ -20,17 +49,67 @@ fn _apply_offset(shifted: i8) -> char {
 }
 
 pub trait RotorEncode {
-    fn new(ring_setting: char, init_position: char) -> Self;
+    /// Construct a rotor without checking its settings; both must be `A`-`Z`.
+    /// Prefer [`RotorEncode::try_new`] for settings that come from a user.
+    fn new(ring_setting: char, init_position: char) -> Self
+    where
+        Self: Sized;
     fn _shift_input(&self, input: char) -> char;
     fn _shift_output(&self, output: char) -> char;
     fn transpose_in(&self, input: char) -> char;
     fn transpose_out(&self, input: char) -> char;
     fn advance(&mut self);
+    /// Move the ring against the wiring, leaving the window letter alone.
+    fn set_ring_setting(&mut self, ring_setting: char);
+    /// Change the position that [`RotorEncode::reset`] returns to.
+    fn set_init_position(&mut self, init_position: char);
+    /// Turn the rotor to show `position` in the window.
+    fn set_position(&mut self, position: char);
+    /// The name the rotor goes by in key notation, such as `IV`.
+    fn name(&self) -> &'static str;
     fn ring_setting(&self) -> char;
     fn init_position(&self) -> char;
     fn position(&self) -> char;
     fn get_offset(&self) -> i8;
     fn at_notch(&self) -> bool;
+
+    /// The substitution this rotor performs on the way in, at its current
+    /// position and ring setting.
+    fn permutation(&self) -> Permutation {
+        Permutation::from_fn(|c| self.transpose_in(c)).expect("rotor wiring is a permutation")
+    }
+
+    /// Turn the rotor back to the position it was set to.
+    fn reset(&mut self) {
+        self.set_position(self.init_position());
+    }
+
+    fn try_new(ring_setting: char, init_position: char) -> Result<Self, RotorError>
+    where
+        Self: Sized,
+    {
+        if !ring_setting.is_ascii_uppercase() {
+            return Err(RotorError::InvalidRingSetting(ring_setting));
+        }
+
+        if !init_position.is_ascii_uppercase() {
+            return Err(RotorError::InvalidPosition(init_position));
+        }
+
+        Ok(Self::new(ring_setting, init_position))
+    }
+
+    /// Construct a rotor from a numbered ring setting, `01`-`26`, as printed
+    /// on Army key sheets.
+    fn with_ring_number(ring_setting: u8, init_position: char) -> Result<Self, RotorError>
+    where
+        Self: Sized,
+    {
+        match ring_setting {
+            1..=26 => Self::try_new((b'A' + ring_setting - 1) as char, init_position),
+            n => Err(RotorError::InvalidRingNumber(n)),
+        }
+    }
 }
 
 #[derive(RotorEncode)]
@@ -83,9 +162,136 @@ pub struct RotorV {
     cur_offset: u8,
 }
 
+#[derive(RotorEncode)]
+#[key_ordering(JPGVOUMFYQBENHZRDKASXLICTW)]
+#[notches(ZM)]
+pub struct RotorVI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[key_ordering(NZJHGRCXMYSWBOUFAIVLPEKQDT)]
+#[notches(ZM)]
+pub struct RotorVII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+#[derive(RotorEncode)]
+#[key_ordering(FKQHTLXOCBJSPDZRAMEWNIUYGV)]
+#[notches(ZM)]
+pub struct RotorVIII {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+/// A rotor whose wiring core has been turned round before going into its
+/// ring, as Typex allowed. The ring, and so the notches and the letters in the
+/// window, are unchanged; only the wiring is mirrored.
+pub struct Reversed<R>(pub R);
+
+fn _mirror(input: char) -> char {
+    ((26 - (input as u8 - b'A')) % 26 + b'A') as char
+}
+
+impl<R: RotorEncode> Reversed<R> {
+    /// Look up `input` in the mirrored wiring, using `lookup` to reach the
+    /// inner rotor's wiring in the other direction.
+    fn _through<F: Fn(&R, char) -> char>(&self, input: char, lookup: F) -> char {
+        let contact = _mirror(self.0._shift_input(input));
+        let wired = self
+            .0
+            ._shift_input(lookup(&self.0, self.0._shift_output(contact)));
+
+        self.0._shift_output(_mirror(wired))
+    }
+}
+
+impl<R: RotorEncode> RotorEncode for Reversed<R> {
+    fn new(ring_setting: char, init_position: char) -> Self {
+        Reversed(R::new(ring_setting, init_position))
+    }
+
+    fn _shift_input(&self, input: char) -> char {
+        self.0._shift_input(input)
+    }
+
+    fn _shift_output(&self, output: char) -> char {
+        self.0._shift_output(output)
+    }
+
+    fn transpose_in(&self, input: char) -> char {
+        self._through(input, |rotor, c| rotor.transpose_out(c))
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        self._through(input, |rotor, c| rotor.transpose_in(c))
+    }
+
+    fn advance(&mut self) {
+        self.0.advance()
+    }
+
+    fn set_ring_setting(&mut self, ring_setting: char) {
+        self.0.set_ring_setting(ring_setting)
+    }
+
+    fn set_init_position(&mut self, init_position: char) {
+        self.0.set_init_position(init_position)
+    }
+
+    fn set_position(&mut self, position: char) {
+        self.0.set_position(position)
+    }
+
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn ring_setting(&self) -> char {
+        self.0.ring_setting()
+    }
+
+    fn init_position(&self) -> char {
+        self.0.init_position()
+    }
+
+    fn position(&self) -> char {
+        self.0.position()
+    }
+
+    fn get_offset(&self) -> i8 {
+        self.0.get_offset()
+    }
+
+    fn at_notch(&self) -> bool {
+        self.0.at_notch()
+    }
+}
+
+/// The thin Greek wheel of the naval M4, which sits beside the reflector and
+/// never steps.
+#[derive(RotorEncode)]
+#[key_ordering(LEYJVCNIXWPBQMDRTAKZGFUHOS)]
+pub struct RotorBeta {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "alloc")]
+    use alloc::format;
 
     #[test]
     fn test_transpose_in() {
@@ -116,4 +322,126 @@ mod test {
         let rotor = RotorI::new('F', 'Y');
         assert_eq!(rotor.transpose_out('W'), 'A');
     }
+
+    #[test]
+    fn test_try_new() {
+        let rotor = RotorI::try_new('F', 'Y').unwrap();
+        assert_eq!(rotor.transpose_in('A'), 'W');
+
+        assert_eq!(
+            RotorI::try_new('f', 'Y').err(),
+            Some(RotorError::InvalidRingSetting('f'))
+        );
+        assert_eq!(
+            RotorI::try_new('F', '9').err(),
+            Some(RotorError::InvalidPosition('9'))
+        );
+        assert_eq!(
+            RotorI::try_new('É', 'A').err(),
+            Some(RotorError::InvalidRingSetting('É'))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_with_ring_number() {
+        let rotor = RotorI::with_ring_number(6, 'Y').unwrap();
+        assert_eq!(rotor.ring_setting(), 'F');
+        assert_eq!(rotor.transpose_in('A'), 'W');
+
+        let rotor = RotorI::with_ring_number(26, 'A').unwrap();
+        assert_eq!(rotor.ring_setting(), 'Z');
+
+        assert_eq!(
+            RotorI::with_ring_number(0, 'A').err(),
+            Some(RotorError::InvalidRingNumber(0))
+        );
+        assert_eq!(
+            RotorI::with_ring_number(27, 'A').err(),
+            Some(RotorError::InvalidRingNumber(27))
+        );
+        assert_eq!(
+            RotorI::with_ring_number(1, 'a').err(),
+            Some(RotorError::InvalidPosition('a'))
+        );
+        assert_eq!(
+            format!("{}", RotorError::InvalidRingNumber(0)),
+            "00 is not a valid ring setting, must be 01-26"
+        );
+    }
+
+    #[test]
+    fn test_setters() {
+        let mut rotor = RotorI::new('A', 'A');
+        rotor.set_ring_setting('F');
+        rotor.set_position('Y');
+
+        assert_eq!(rotor.position(), 'Y');
+        assert_eq!(rotor.permutation(), RotorI::new('F', 'Y').permutation());
+
+        rotor.set_init_position('C');
+        rotor.reset();
+        assert_eq!(rotor.permutation(), RotorI::new('F', 'C').permutation());
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut rotor = RotorII::new('C', 'X');
+        for _ in 0..30 {
+            rotor.advance();
+        }
+        rotor.reset();
+
+        assert_eq!(rotor.position(), 'X');
+        assert_eq!(rotor.permutation(), RotorII::new('C', 'X').permutation());
+    }
+
+    #[test]
+    fn test_reversed() {
+        // Rotor I's wiring, mirrored: x -> -I^-1(-x).
+        crate::rotor!(RotorIMirrored, "GRMKNSJPIDTHOQYWZBFLVXAUCE", notches = "Q");
+
+        for (ring, position) in [('A', 'A'), ('F', 'Y'), ('Q', 'C')] {
+            let mut reversed = Reversed::<RotorI>::new(ring, position);
+            let mut mirrored = RotorIMirrored::new(ring, position);
+
+            for _ in 0..30 {
+                assert_eq!(reversed.permutation(), mirrored.permutation());
+                assert_eq!(reversed.at_notch(), mirrored.at_notch());
+                assert_eq!(reversed.position(), mirrored.position());
+
+                reversed.advance();
+                mirrored.advance();
+            }
+        }
+
+        let twice = Reversed(Reversed(RotorIII::new('D', 'K')));
+        assert_eq!(twice.permutation(), RotorIII::new('D', 'K').permutation());
+    }
+
+    #[test]
+    fn test_advance_wraps() {
+        let mut rotor = RotorI::new('A', 'Z');
+        rotor.advance();
+        assert_eq!(rotor.position(), 'A');
+
+        let mut rotor = RotorVI::new('A', 'Y');
+        assert!(!rotor.at_notch());
+        rotor.advance();
+        assert!(rotor.at_notch());
+        rotor.advance();
+        assert_eq!(rotor.position(), 'A');
+    }
+
+    #[test]
+    fn test_permutation() {
+        let wiring = Permutation::from_wiring("EKMFLGDQVZNTOWYHXUSPAIBRCJ").unwrap();
+
+        let rotor = RotorI::new('A', 'A');
+        assert_eq!(rotor.permutation(), wiring);
+
+        let rotor = RotorI::new('F', 'Y');
+        assert_eq!(rotor.permutation(), wiring.conjugate(24 - 5));
+        assert_eq!(rotor.permutation().inverse().apply('W'), 'A');
+    }
 }
@@ -0,0 +1,432 @@
+// src/settings.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! A machine's whole key in one place, written the way key sheets give it:
+//!
+//! ```text
+//! B I-II-III 01-01-01 AAA AB CD
+//! ```
+//!
+//! That is the reflector, the wheel order from left to right, the ring
+//! settings, the window letters and the plugboard pairs. Where the reflector
+//! can be set by hand, its letter leads the window letters, so a G-312 reads
+//! `UKW I-II-III 01-01-01 ZAAA`. A stecker that is not reciprocal, such as
+//! the Uhr, is written as its 26-letter wiring instead of pairs.
+
+use crate::permutation::{Permutation, PermutationError};
+use crate::plugboard::{Plugboard, PlugboardError};
+use crate::reflectors::{Reflector, SettableReflector};
+use crate::rotors::RotorEncode;
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+/// Why a key in the notation of [`MachineSettings`] could not be read. This is
+/// a problem with the text, not with any machine, so it stands apart from
+/// [`EnigmaError`](crate::EnigmaError).
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SettingsError {
+    MissingField(&'static str),
+    /// The ring settings or window letters do not match the wheel order.
+    WheelCount {
+        field: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    InvalidRingSetting(String),
+    InvalidPosition(char),
+    Plugboard(PlugboardError),
+    Stecker(PermutationError),
+}
+
+impl Error for SettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SettingsError::Plugboard(err) => Some(err),
+            SettingsError::Stecker(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::MissingField(field) => write!(f, "missing {field}"),
+            SettingsError::WheelCount {
+                field,
+                expected,
+                found,
+            } => write!(f, "expected {expected} {field}, found {found}"),
+            SettingsError::InvalidRingSetting(ring) => {
+                write!(
+                    f,
+                    "'{ring}' is not a valid ring setting, must be 01-26 or A-Z"
+                )
+            }
+            SettingsError::InvalidPosition(c) => {
+                write!(f, "'{c}' is not a valid position, must be A-Z")
+            }
+            SettingsError::Plugboard(err) => write!(f, "invalid plugboard: {err}"),
+            SettingsError::Stecker(err) => write!(f, "invalid stecker: {err}"),
+        }
+    }
+}
+
+impl From<PlugboardError> for SettingsError {
+    fn from(err: PlugboardError) -> Self {
+        SettingsError::Plugboard(err)
+    }
+}
+
+impl From<PermutationError> for SettingsError {
+    fn from(err: PermutationError) -> Self {
+        SettingsError::Stecker(err)
+    }
+}
+
+/// One rotor as it sits in the machine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RotorSettings {
+    pub name: String,
+    pub ring_setting: char,
+    pub position: char,
+    /// Whether the rotor will carry its neighbour on the next keypress. This
+    /// is not part of the key notation, so parsed settings never set it.
+    pub at_notch: bool,
+}
+
+impl RotorSettings {
+    pub fn of<R: RotorEncode + ?Sized>(rotor: &R) -> Self {
+        RotorSettings {
+            name: rotor.name().to_owned(),
+            ring_setting: rotor.ring_setting(),
+            position: rotor.position(),
+            at_notch: rotor.at_notch(),
+        }
+    }
+
+    /// The ring setting as numbered on Army key sheets, `1`-`26`.
+    pub fn ring_number(&self) -> u8 {
+        self.ring_setting as u8 - b'A' + 1
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MachineSettings {
+    pub reflector: String,
+    /// The reflector's position, for machines where it can be set.
+    pub reflector_position: Option<char>,
+    /// The rotors from left to right, including any that never step.
+    pub rotors: Vec<RotorSettings>,
+    pub stecker: Permutation,
+}
+
+impl MachineSettings {
+    pub(crate) fn with_reflector<D: Reflector + ?Sized>(
+        reflector: &D,
+        rotors: Vec<RotorSettings>,
+        stecker: Option<Permutation>,
+    ) -> Self {
+        MachineSettings {
+            reflector: reflector.name().to_owned(),
+            reflector_position: None,
+            rotors,
+            stecker: stecker.unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn with_settable_reflector<D: SettableReflector>(
+        reflector: &D,
+        rotors: Vec<RotorSettings>,
+    ) -> Self {
+        MachineSettings {
+            reflector_position: Some(reflector.position()),
+            ..Self::with_reflector(reflector, rotors, None)
+        }
+    }
+
+    /// The plugboard cables, if the stecker is reciprocal.
+    pub fn plugboard_pairs(&self) -> Option<Vec<(char, char)>> {
+        if !self.stecker.is_involution() {
+            return None;
+        }
+
+        let pairs = ('A'..='Z')
+            .map(|left| (left, self.stecker.apply(left)))
+            .filter(|(left, right)| left < right)
+            .collect();
+
+        Some(pairs)
+    }
+
+    /// The window letters, led by the reflector's where it can be set.
+    pub fn positions(&self) -> Vec<char> {
+        self.reflector_position
+            .into_iter()
+            .chain(self.rotors.iter().map(|rotor| rotor.position))
+            .collect()
+    }
+}
+
+fn _parse_ring(ring: &str) -> Result<char, SettingsError> {
+    let invalid = || SettingsError::InvalidRingSetting(ring.to_owned());
+    let mut letters = ring.chars();
+
+    match (letters.next(), letters.next()) {
+        (Some(c @ 'A'..='Z'), None) => Ok(c),
+        _ => match ring.parse::<u8>() {
+            Ok(n @ 1..=26) => Ok((b'A' + n - 1) as char),
+            _ => Err(invalid()),
+        },
+    }
+}
+
+fn _parse_stecker(groups: &[&str]) -> Result<Permutation, SettingsError> {
+    match groups {
+        [wiring] if wiring.len() == 26 => Ok(Permutation::from_wiring(wiring)?),
+        _ => Ok(groups.join(" ").parse::<Plugboard>()?.permutation()),
+    }
+}
+
+impl FromStr for MachineSettings {
+    type Err = SettingsError;
+
+    /// Parse key notation, e.g. `"B I-II-III 01-01-01 AAA AB CD"`. Ring
+    /// settings may be numbers or letters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let groups: Vec<&str> = s.split_whitespace().collect();
+
+        let (reflector, order, rings, positions, stecker) = match groups[..] {
+            [reflector, order, rings, positions, ref stecker @ ..] => {
+                (reflector, order, rings, positions, stecker)
+            }
+            [_, _, _] => return Err(SettingsError::MissingField("window letters")),
+            [_, _] => return Err(SettingsError::MissingField("ring settings")),
+            [_] => return Err(SettingsError::MissingField("wheel order")),
+            [] => return Err(SettingsError::MissingField("reflector")),
+        };
+
+        let names: Vec<&str> = order.split('-').collect();
+        let rings: Vec<&str> = rings.split('-').collect();
+        let mut positions: Vec<char> = positions.chars().collect();
+
+        if rings.len() != names.len() {
+            return Err(SettingsError::WheelCount {
+                field: "ring settings",
+                expected: names.len(),
+                found: rings.len(),
+            });
+        }
+
+        let reflector_position = match positions.len().checked_sub(names.len()) {
+            Some(0) => None,
+            Some(1) => Some(positions.remove(0)),
+            _ => {
+                return Err(SettingsError::WheelCount {
+                    field: "window letters",
+                    expected: names.len(),
+                    found: positions.len(),
+                })
+            }
+        };
+
+        if let Some(c) = reflector_position
+            .iter()
+            .chain(positions.iter())
+            .find(|c| !c.is_ascii_uppercase())
+        {
+            return Err(SettingsError::InvalidPosition(*c));
+        }
+
+        let rotors = names
+            .iter()
+            .zip(rings.iter())
+            .zip(positions.iter())
+            .map(|((name, ring), position)| {
+                Ok(RotorSettings {
+                    name: (*name).to_owned(),
+                    ring_setting: _parse_ring(ring)?,
+                    position: *position,
+                    at_notch: false,
+                })
+            })
+            .collect::<Result<Vec<_>, SettingsError>>()?;
+
+        Ok(MachineSettings {
+            reflector: reflector.to_owned(),
+            reflector_position,
+            rotors,
+            stecker: _parse_stecker(stecker)?,
+        })
+    }
+}
+
+impl fmt::Display for MachineSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.reflector)?;
+
+        for (n, rotor) in self.rotors.iter().enumerate() {
+            let sep = if n > 0 { "-" } else { "" };
+            write!(f, "{sep}{}", rotor.name)?;
+        }
+
+        write!(f, " ")?;
+
+        for (n, rotor) in self.rotors.iter().enumerate() {
+            let sep = if n > 0 { "-" } else { "" };
+            write!(f, "{sep}{:02}", rotor.ring_number())?;
+        }
+
+        write!(f, " ")?;
+
+        for position in self.positions() {
+            write!(f, "{position}")?;
+        }
+
+        match self.plugboard_pairs() {
+            Some(pairs) => {
+                for (left, right) in pairs {
+                    write!(f, " {left}{right}")?;
+                }
+            }
+            None => write!(f, " {}", self.stecker.wiring())?,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn rotor(name: &str, ring_setting: char, position: char) -> RotorSettings {
+        RotorSettings {
+            name: name.into(),
+            ring_setting,
+            position,
+            at_notch: false,
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let settings = MachineSettings {
+            reflector: "B".into(),
+            reflector_position: None,
+            rotors: vec![
+                rotor("I", 'A', 'A'),
+                rotor("II", 'A', 'A'),
+                rotor("III", 'A', 'A'),
+            ],
+            stecker: "AB CD".parse::<Plugboard>().unwrap().permutation(),
+        };
+
+        assert_eq!(settings.to_string(), "B I-II-III 01-01-01 AAA AB CD");
+        assert_eq!(
+            settings.plugboard_pairs(),
+            Some(vec![('A', 'B'), ('C', 'D')])
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let settings: MachineSettings = "C IV-II-V 07-13-26 VDY AT BL".parse().unwrap();
+
+        assert_eq!(settings.reflector, "C");
+        assert_eq!(settings.reflector_position, None);
+        assert_eq!(
+            settings.rotors,
+            vec![
+                rotor("IV", 'G', 'V'),
+                rotor("II", 'M', 'D'),
+                rotor("V", 'Z', 'Y')
+            ]
+        );
+        assert_eq!(
+            settings.plugboard_pairs(),
+            Some(vec![('A', 'T'), ('B', 'L')])
+        );
+
+        let letters: MachineSettings = "C IV-II-V G-M-Z VDY AT BL".parse().unwrap();
+        assert_eq!(letters, settings);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for notation in [
+            "B I-II-III 01-01-01 AAA",
+            "B-thin Beta-II-IV-I 01-01-01-22 AAAA AT BL DF GJ",
+            "UKW I-II-III 05-20-11 ZAQR",
+            "B I-II-III 01-01-01 AAA BCDEFGHIJKLMNOPQRSTUVWXYZA",
+        ] {
+            let settings: MachineSettings = notation.parse().unwrap();
+            assert_eq!(settings.to_string(), notation);
+        }
+
+        let settable: MachineSettings = "UKW I-II-III 05-20-11 ZAQR".parse().unwrap();
+        assert_eq!(settable.reflector_position, Some('Z'));
+        assert_eq!(settable.positions(), vec!['Z', 'A', 'Q', 'R']);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |s: &str| s.parse::<MachineSettings>().unwrap_err();
+
+        assert_eq!(parse(""), SettingsError::MissingField("reflector"));
+        assert_eq!(
+            parse("B I-II-III 01-01"),
+            SettingsError::MissingField("window letters")
+        );
+        assert_eq!(
+            parse("B I-II-III 01-01 AAA"),
+            SettingsError::WheelCount {
+                field: "ring settings",
+                expected: 3,
+                found: 2
+            }
+        );
+        assert_eq!(
+            parse("B I-II-III 01-01-01 AAAAA"),
+            SettingsError::WheelCount {
+                field: "window letters",
+                expected: 3,
+                found: 5
+            }
+        );
+        assert_eq!(
+            parse("B I-II-III 01-01-01 AA"),
+            SettingsError::WheelCount {
+                field: "window letters",
+                expected: 3,
+                found: 2
+            }
+        );
+        assert_eq!(
+            parse("B I-II-III 01-27-01 AAA"),
+            SettingsError::InvalidRingSetting("27".into())
+        );
+        assert_eq!(
+            parse("B I-II-III 01-01-01 AaA"),
+            SettingsError::InvalidPosition('a')
+        );
+        assert_eq!(
+            parse("B I-II-III 01-01-01 AAA AB BC"),
+            SettingsError::Plugboard(PlugboardError::CharacterAlreadyWired('B'))
+        );
+    }
+}
@@ -0,0 +1,280 @@
+// src/snapshot.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+#[cfg(feature = "alloc")]
+use crate::permutation::Permutation;
+#[cfg(feature = "alloc")]
+use crate::plugboard::Stecker;
+#[cfg(feature = "alloc")]
+use crate::rotors::RotorEncode;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::error::Error;
+use core::fmt;
+
+/// Why a snapshot does not fit a machine. Machines hand it back wrapped in
+/// [`EnigmaError::Snapshot`](crate::EnigmaError::Snapshot).
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    RotorCount {
+        expected: usize,
+        found: usize,
+    },
+    InvalidLetter(char),
+    /// The snapshot was taken with a different rotor in this slot, counted
+    /// from 1.
+    WrongRotor {
+        slot: usize,
+        expected: &'static str,
+    },
+    /// The snapshot's stecker cannot be set up on this machine's stecker.
+    Stecker,
+    /// The snapshot has a reflector position and the machine has no settable
+    /// reflector, or the other way round.
+    Reflector,
+}
+
+impl Error for SnapshotError {}
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::RotorCount { expected, found } => {
+                write!(f, "expected {expected} rotors, found {found}")
+            }
+            SnapshotError::InvalidLetter(c) => {
+                write!(f, "'{c}' is not a valid setting, must be A-Z")
+            }
+            SnapshotError::WrongRotor { slot, expected } => {
+                write!(f, "the saved rotor in slot {slot} is not rotor {expected}")
+            }
+            SnapshotError::Stecker => {
+                write!(f, "the stecker cannot be set to the saved substitution")
+            }
+            SnapshotError::Reflector => {
+                write!(f, "the saved reflector does not match the machine")
+            }
+        }
+    }
+}
+
+fn _check_letter(letter: char) -> Result<(), SnapshotError> {
+    match letter {
+        'A'..='Z' => Ok(()),
+        c => Err(SnapshotError::InvalidLetter(c)),
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RotorState {
+    /// Which rotor this is, as [`RotorEncode::name`] gives it.
+    pub name: String,
+    pub ring_setting: char,
+    pub init_position: char,
+    pub position: char,
+}
+
+#[cfg(feature = "alloc")]
+impl RotorState {
+    pub fn of<R: RotorEncode + ?Sized>(rotor: &R) -> Self {
+        RotorState {
+            name: rotor.name().into(),
+            ring_setting: rotor.ring_setting(),
+            init_position: rotor.init_position(),
+            position: rotor.position(),
+        }
+    }
+
+    pub(crate) fn apply<R: RotorEncode + ?Sized>(&self, rotor: &mut R) {
+        rotor.set_ring_setting(self.ring_setting);
+        rotor.set_init_position(self.init_position);
+        rotor.set_position(self.position);
+    }
+}
+
+/// Where a settable reflector was set, and where it is now.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReflectorState {
+    pub init_position: char,
+    pub position: char,
+}
+
+/// Everything needed to put a machine back exactly as it was: every rotor's
+/// name, ring setting, starting position and current position from left to
+/// right, the reflector's position where it can be set, and the stecker.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub rotors: Vec<RotorState>,
+    pub reflector: Option<ReflectorState>,
+    pub stecker: Option<Permutation>,
+}
+
+#[cfg(feature = "alloc")]
+impl Snapshot {
+    /// Check the snapshot fits a machine before anything is changed.
+    /// `rotors` names the machine's rotors, from left to right.
+    pub(crate) fn check(
+        &self,
+        rotors: &[&'static str],
+        settable_reflector: bool,
+    ) -> Result<(), SnapshotError> {
+        if self.rotors.len() != rotors.len() {
+            return Err(SnapshotError::RotorCount {
+                expected: rotors.len(),
+                found: self.rotors.len(),
+            });
+        }
+
+        for (n, (state, name)) in self.rotors.iter().zip(rotors).enumerate() {
+            if state.name != *name {
+                return Err(SnapshotError::WrongRotor {
+                    slot: n + 1,
+                    expected: name,
+                });
+            }
+        }
+
+        if self.reflector.is_some() != settable_reflector {
+            return Err(SnapshotError::Reflector);
+        }
+
+        for state in self.rotors.iter() {
+            _check_letter(state.ring_setting)?;
+            _check_letter(state.init_position)?;
+            _check_letter(state.position)?;
+        }
+
+        if let Some(state) = &self.reflector {
+            _check_letter(state.init_position)?;
+            _check_letter(state.position)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) fn _snapshot_stecker<E: Stecker>(stecker: &Option<E>) -> Option<Permutation> {
+    stecker.as_ref().map(|s| s.permutation())
+}
+
+/// A machine without a stecker can only take a snapshot with none.
+#[cfg(feature = "alloc")]
+pub(crate) fn _restore_stecker<E: Stecker>(
+    stecker: &mut Option<E>,
+    saved: Option<&Permutation>,
+) -> Result<(), SnapshotError> {
+    let target = saved.copied().unwrap_or_default();
+
+    match stecker {
+        Some(stecker) => stecker.restore(&target),
+        None if target.is_identity() => Ok(()),
+        None => Err(SnapshotError::Stecker),
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::*;
+    use crate::plugboard::Plugboard;
+    use crate::rotors::RotorII;
+
+    use alloc::vec;
+
+    #[test]
+    fn test_rotor_state() {
+        let mut rotor = RotorII::new('C', 'X');
+        rotor.advance();
+
+        let state = RotorState::of(&rotor);
+        assert_eq!(
+            state,
+            RotorState {
+                name: "II".into(),
+                ring_setting: 'C',
+                init_position: 'X',
+                position: 'Y',
+            }
+        );
+
+        let mut other = RotorII::new('A', 'A');
+        state.apply(&mut other);
+        assert_eq!(other.permutation(), rotor.permutation());
+
+        other.reset();
+        assert_eq!(other.position(), 'X');
+    }
+
+    #[test]
+    fn test_check() {
+        let state = RotorState {
+            name: "I".into(),
+            ring_setting: 'A',
+            init_position: 'A',
+            position: 'A',
+        };
+        let snapshot = Snapshot {
+            rotors: vec![state; 3],
+            reflector: None,
+            stecker: None,
+        };
+
+        assert_eq!(snapshot.check(&["I"; 3], false), Ok(()));
+        assert_eq!(
+            snapshot.check(&["I"; 4], false),
+            Err(SnapshotError::RotorCount {
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(
+            snapshot.check(&["I", "II", "I"], false),
+            Err(SnapshotError::WrongRotor {
+                slot: 2,
+                expected: "II"
+            })
+        );
+        assert_eq!(
+            snapshot.check(&["I"; 3], true),
+            Err(SnapshotError::Reflector)
+        );
+
+        let mut snapshot = snapshot;
+        snapshot.rotors[1].position = 'a';
+        assert_eq!(
+            snapshot.check(&["I"; 3], false),
+            Err(SnapshotError::InvalidLetter('a'))
+        );
+    }
+
+    #[test]
+    fn test_restore_stecker() {
+        let mut stecker = Some(Plugboard::new(&[('A', 'B')]).unwrap());
+        let saved = Plugboard::new(&[('Q', 'X'), ('E', 'R')])
+            .unwrap()
+            .permutation();
+
+        assert_eq!(_restore_stecker(&mut stecker, Some(&saved)), Ok(()));
+        assert_eq!(_snapshot_stecker(&stecker), Some(saved));
+
+        let mut none: Option<Plugboard> = None;
+        assert_eq!(_restore_stecker(&mut none, None), Ok(()));
+        assert_eq!(
+            _restore_stecker(&mut none, Some(&saved)),
+            Err(SnapshotError::Stecker)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+// src/stepping.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::reflectors::SettableReflector;
+use crate::rotors::RotorEncode;
+
+/// What a stepping mechanism sees of a wheel: where it is, whether it is on
+/// a notch, and how to move it on.
+pub trait Wheel {
+    fn position(&self) -> char;
+    fn at_notch(&self) -> bool;
+    fn advance(&mut self);
+}
+
+impl<T: RotorEncode + ?Sized> Wheel for T {
+    fn position(&self) -> char {
+        RotorEncode::position(self)
+    }
+
+    fn at_notch(&self) -> bool {
+        RotorEncode::at_notch(self)
+    }
+
+    fn advance(&mut self) {
+        RotorEncode::advance(self)
+    }
+}
+
+/// Puts a turning reflector at the end of a gear train. It has no notches,
+/// since there is nothing beyond it to carry into.
+pub struct ReflectorWheel<'a, D>(pub &'a mut D);
+
+impl<D: SettableReflector> Wheel for ReflectorWheel<'_, D> {
+    fn position(&self) -> char {
+        self.0.position()
+    }
+
+    fn at_notch(&self) -> bool {
+        false
+    }
+
+    fn advance(&mut self) {
+        self.0.advance()
+    }
+}
+
+/// The wheels a stepping mechanism reaches, ordered as they sit in the
+/// machine from left to right, so the fastest wheel comes last. Wheels that
+/// are set by hand and never move are left out.
+pub trait Wheels {
+    fn count(&self) -> usize;
+    fn wheel(&mut self, n: usize) -> &mut dyn Wheel;
+}
+
+impl Wheels for &mut [&mut dyn Wheel] {
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn wheel(&mut self, n: usize) -> &mut dyn Wheel {
+        &mut *self[n]
+    }
+}
+
+/// The mechanism that moves the wheels before each keypress.
+pub trait Stepping {
+    fn step_wheels(&self, wheels: &mut dyn Wheels);
+
+    /// Step wheels listed one by one, as a machine with a fixed set of
+    /// rotors has them.
+    fn step(&self, mut wheels: &mut [&mut dyn Wheel]) {
+        self.step_wheels(&mut wheels)
+    }
+}
+
+/// The lever-and-pawl drive of the service machines. There is one pawl per
+/// wheel; each one past the first rides on the notch of the wheel to its
+/// right and pushes both wheels when it drops in, so a wheel on its own notch
+/// is pushed along a second time -- the double step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pawl;
+
+impl Stepping for Pawl {
+    fn step_wheels(&self, wheels: &mut dyn Wheels) {
+        let last = match wheels.count() {
+            0 => return,
+            n => n - 1,
+        };
+
+        // Work from the left, so every notch is read before its wheel moves.
+        for slot in 0..=last {
+            let moves = slot == last
+                || wheels.wheel(slot + 1).at_notch()
+                || (slot > 0 && wheels.wheel(slot).at_notch());
+
+            if moves {
+                wheels.wheel(slot).advance();
+            }
+        }
+    }
+}
+
+/// Each wheel carries into the next once a revolution, as it leaves `Z`,
+/// regardless of where its notches are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Odometer;
+
+impl Stepping for Odometer {
+    fn step_wheels(&self, wheels: &mut dyn Wheels) {
+        for n in (0..wheels.count()).rev() {
+            let wheel = wheels.wheel(n);
+            let carry = wheel.position() == 'Z';
+            wheel.advance();
+
+            if !carry {
+                break;
+            }
+        }
+    }
+}
+
+/// The gear drive of the Enigma G: each wheel carries into the next whenever
+/// it leaves one of its notches, and there is no double step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cog;
+
+impl Stepping for Cog {
+    fn step_wheels(&self, wheels: &mut dyn Wheels) {
+        for n in (0..wheels.count()).rev() {
+            let wheel = wheels.wheel(n);
+            let carry = wheel.at_notch();
+            wheel.advance();
+
+            if !carry {
+                break;
+            }
+        }
+    }
+}
+
+/// Wheels that are set by hand and never move, like the stators of a Typex.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stationary;
+
+impl Stepping for Stationary {
+    fn step_wheels(&self, _wheels: &mut dyn Wheels) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rotors::*;
+
+    fn assert_positions(wheels: &[&mut dyn Wheel], expected: &str) {
+        assert_eq!(wheels.len(), expected.len());
+        for (wheel, position) in wheels.iter().zip(expected.chars()) {
+            assert_eq!(wheel.position(), position, "expected {expected}");
+        }
+    }
+
+    #[test]
+    fn test_pawl_double_step() {
+        let (mut left, mut middle, mut right) = (
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'D'),
+            RotorIII::new('A', 'U'),
+        );
+        let mut wheels: [&mut dyn Wheel; 3] = [&mut left, &mut middle, &mut right];
+
+        Pawl.step(&mut wheels);
+        assert_positions(&wheels, "ADV");
+        Pawl.step(&mut wheels);
+        assert_positions(&wheels, "AEW");
+        Pawl.step(&mut wheels);
+        assert_positions(&wheels, "BFX");
+    }
+
+    #[test]
+    fn test_pawl_single_wheel() {
+        let mut right = RotorI::new('A', 'Q');
+        let mut wheels: [&mut dyn Wheel; 1] = [&mut right];
+
+        Pawl.step(&mut wheels);
+        assert_positions(&wheels, "R");
+        Pawl.step(&mut []);
+    }
+
+    #[test]
+    fn test_odometer() {
+        let (mut left, mut middle, mut right) = (
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'Z'),
+            RotorIII::new('A', 'Y'),
+        );
+        let mut wheels: [&mut dyn Wheel; 3] = [&mut left, &mut middle, &mut right];
+
+        Odometer.step(&mut wheels);
+        assert_positions(&wheels, "AZZ");
+        Odometer.step(&mut wheels);
+        assert_positions(&wheels, "BAA");
+        Odometer.step(&mut wheels);
+        assert_positions(&wheels, "BAB");
+    }
+
+    #[test]
+    fn test_cog() {
+        // The middle rotor sits on its notch, but stays put until the right
+        // rotor carries into it; then it carries on into the left.
+        let (mut left, mut middle, mut right) = (
+            RotorI::new('A', 'A'),
+            RotorII::new('A', 'E'),
+            RotorIII::new('A', 'U'),
+        );
+        let mut wheels: [&mut dyn Wheel; 3] = [&mut left, &mut middle, &mut right];
+
+        Cog.step(&mut wheels);
+        assert_positions(&wheels, "AEV");
+        Cog.step(&mut wheels);
+        assert_positions(&wheels, "BFW");
+
+        let mut notched = RotorVI::new('A', 'Z');
+        let mut wheels: [&mut dyn Wheel; 2] = [&mut left, &mut notched];
+
+        Cog.step(&mut wheels);
+        assert_positions(&wheels, "CA");
+    }
+
+    #[test]
+    fn test_stationary() {
+        let mut right = RotorI::new('A', 'Q');
+        let mut wheels: [&mut dyn Wheel; 1] = [&mut right];
+
+        Stationary.step(&mut wheels);
+        assert_positions(&wheels, "Q");
+    }
+}
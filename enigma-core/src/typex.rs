@@ -0,0 +1,198 @@
+// src/typex.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+//! The British Typex. Five rotors sit between the reflector and the entry:
+//! the three nearest the reflector step as on an Enigma, while the two next
+//! to the entry are stators, set by hand for the message and then left alone.
+//! Any rotor may be put in [`Reversed`](crate::rotors::Reversed).
+//!
+//! This is the frame of the machine only. No Typex wheel set or reflector
+//! ships with the crate, since no wiring with a source to cite was at hand,
+//! so there is no `Model::Typex` or registry entry yet: the wheels and the
+//! reflector are supplied by the caller.
+
+use crate::machine::{RotorMachine, Slot};
+use crate::permutation::Permutation;
+use crate::plugboard::Stecker;
+use crate::reflectors::Reflector;
+use crate::rotors::RotorEncode;
+use crate::settings::MachineSettings;
+use crate::snapshot::Snapshot;
+use crate::stepping::Pawl;
+use crate::{Enigma, EnigmaError};
+
+use alloc::{vec, vec::Vec};
+
+pub struct Typex<D, E> {
+    machine: RotorMachine<D, E, Pawl>,
+}
+
+impl<D: Reflector, E: Stecker> Typex<D, E> {
+    /// Rotors are listed from the reflector to the entry, so `stator2` is the
+    /// one next to the entry and `rotor3` is the fast rotor.
+    pub fn new<A, B, C, F, G>(
+        rotor1: A,
+        rotor2: B,
+        rotor3: C,
+        stator1: F,
+        stator2: G,
+        reflector: D,
+        entry: E,
+    ) -> Self
+    where
+        A: RotorEncode + 'static,
+        B: RotorEncode + 'static,
+        C: RotorEncode + 'static,
+        F: RotorEncode + 'static,
+        G: RotorEncode + 'static,
+    {
+        let slots = vec![
+            Slot::moving(rotor1),
+            Slot::moving(rotor2),
+            Slot::moving(rotor3),
+            Slot::fixed(stator1),
+            Slot::fixed(stator2),
+        ];
+
+        Typex {
+            machine: RotorMachine::with_stecker(slots, reflector, Pawl, entry),
+        }
+    }
+}
+
+impl<D: Reflector, E: Stecker> Enigma for Typex<D, E> {
+    fn reset(&mut self) {
+        self.machine.reset()
+    }
+
+    fn keypress(&mut self, input: char) -> Result<char, EnigmaError> {
+        self.machine.keypress(input)
+    }
+
+    /// The substitution made by the entry, whether plugboard or fixed wiring.
+    fn plugboard_transpose(&self, input: char) -> char {
+        self.machine.plugboard_transpose(input)
+    }
+
+    fn settings(&self) -> Vec<char> {
+        self.machine.settings()
+    }
+
+    fn set_ring_settings(&mut self, ring_settings: &[char]) -> Result<(), EnigmaError> {
+        self.machine.set_ring_settings(ring_settings)
+    }
+
+    fn set_positions(&mut self, positions: &[char]) -> Result<(), EnigmaError> {
+        self.machine.set_positions(positions)
+    }
+
+    fn machine_settings(&self) -> MachineSettings {
+        self.machine.machine_settings()
+    }
+
+    fn permutation(&self) -> Permutation {
+        self.machine.permutation()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.machine.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), EnigmaError> {
+        self.machine.restore(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reflectors::ReflectorB;
+    use crate::rotor;
+    use crate::rotors::{Reversed, RotorI, RotorII, RotorIII, RotorIV, RotorV};
+
+    // Stand-in wheels, not Typex wirings, with five-notch rings to exercise
+    // its stepping. The Enigma reflector B stands in for a Typex reflector.
+    rotor!(RotorX, "MYLXZEQNSBKRFWCHUDVOTIAGJP", notches = "ACEIN");
+    rotor!(RotorY, "TBUGYKNQVJXPSOFHZCLMWRIEAD", notches = "ACEIN");
+
+    fn entry() -> Permutation {
+        Permutation::from_wiring("QWERTZUIOASDFGHJKPYXCVBNML").unwrap()
+    }
+
+    fn machine() -> Typex<ReflectorB, Permutation> {
+        Typex::new(
+            RotorX::new('A', 'A'),
+            Reversed(RotorY::new('C', 'Q')),
+            RotorIII::new('A', 'B'),
+            RotorIV::new('K', 'E'),
+            Reversed(RotorV::new('A', 'J')),
+            ReflectorB,
+            entry(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut typex = machine();
+        let plaintext = "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG";
+        let ciphertext = typex.encipher_str(plaintext).unwrap();
+
+        assert_ne!(ciphertext, plaintext);
+        assert!(ciphertext
+            .chars()
+            .zip(plaintext.chars())
+            .all(|(c, p)| c != p));
+
+        typex.reset();
+        assert_eq!(typex.settings(), vec!['A', 'Q', 'B', 'E', 'J']);
+        assert_eq!(typex.encipher_str(&ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_stators_stay_put() {
+        let mut typex = machine();
+
+        for _ in 0..100 {
+            typex.keypress('A').unwrap();
+            assert_eq!(&typex.settings()[3..], &['E', 'J']);
+        }
+    }
+
+    #[test]
+    fn test_stepping() {
+        let mut typex = Typex::new(
+            RotorI::new('A', 'A'),
+            RotorY::new('A', 'B'),
+            RotorX::new('A', 'A'),
+            RotorII::new('A', 'A'),
+            RotorIII::new('A', 'A'),
+            ReflectorB,
+            Permutation::identity(),
+        );
+
+        // The fast rotor leaves a notch on the first press, and the middle
+        // rotor then double-steps out of its own notch at C.
+        typex.keypress('A').unwrap();
+        assert_eq!(&typex.settings()[..3], &['A', 'C', 'B']);
+        typex.keypress('A').unwrap();
+        assert_eq!(&typex.settings()[..3], &['B', 'D', 'C']);
+        typex.keypress('A').unwrap();
+        assert_eq!(&typex.settings()[..3], &['B', 'E', 'D']);
+    }
+
+    #[test]
+    fn test_entry() {
+        let typex = machine();
+        let perm = typex.permutation();
+
+        assert_eq!(typex.plugboard_transpose('A'), 'Q');
+        assert!(perm.is_involution());
+        assert!(perm.fixed_points().is_empty());
+    }
+}
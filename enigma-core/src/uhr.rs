@@ -0,0 +1,274 @@
+// src/uhr.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use crate::permutation::Permutation;
+use crate::plugboard::Stecker;
+use crate::snapshot::SnapshotError;
+
+use core::error::Error;
+use core::fmt;
+
+/// The disk inside the Uhr: contact `n` on the `a` side is wired to contact
+/// `UHR_WIRING[n]` on the `b` side.
+const UHR_WIRING: [u8; 40] = [
+    6, 31, 4, 29, 18, 39, 16, 25, 30, 23, 28, 1, 38, 11, 36, 37, 26, 27, 24, 21, 14, 3, 12, 17, 2,
+    7, 0, 33, 10, 35, 8, 5, 22, 19, 20, 13, 34, 15, 32, 9,
+];
+
+/// Which group of four `b`-side contacts belongs to sockets `1b` through `10b`.
+/// The `a` sockets sit in order; the `b` sockets are arranged so that at 00
+/// each cable's two plugs are joined, just like an ordinary stecker cable.
+const B_SOCKETS: [usize; 10] = [1, 4, 7, 9, 6, 3, 0, 2, 5, 8];
+
+#[derive(Debug, PartialEq)]
+pub enum UhrError {
+    InvalidCharacter(char),
+    CharacterAlreadyWired(char),
+    CableCount(usize),
+    InvalidPosition(u8),
+}
+
+impl Error for UhrError {}
+impl fmt::Display for UhrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UhrError::InvalidCharacter(c) => {
+                write!(f, "'{c}' is not an uppercase ASCII letter")
+            }
+            UhrError::CharacterAlreadyWired(c) => {
+                write!(f, "'{c}' already wired to the Uhr")
+            }
+            UhrError::CableCount(n) => {
+                write!(f, "the Uhr takes exactly 10 cables, found {n}")
+            }
+            UhrError::InvalidPosition(n) => {
+                write!(f, "{n:02} is not a valid Uhr position, must be 00-39")
+            }
+        }
+    }
+}
+
+/// The Luftwaffe's Enigma Uhr, which replaced the ten plugboard cables with a
+/// switch of 40 positions. Each cable has an `a` plug and a `b` plug; except
+/// at a handful of positions, the resulting stecker is not reciprocal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Uhr {
+    a_plugs: [char; 10],
+    b_plugs: [char; 10],
+    position: u8,
+    forward: Permutation,
+}
+
+impl Uhr {
+    /// `cables[n]` gives the letters receiving plugs `(n+1)a` and `(n+1)b`.
+    pub fn new(cables: &[(char, char)], position: u8) -> Result<Self, UhrError> {
+        if cables.len() != 10 {
+            return Err(UhrError::CableCount(cables.len()));
+        }
+
+        let mut a_plugs = ['A'; 10];
+        let mut b_plugs = ['A'; 10];
+        let mut wired = [false; 26];
+
+        for (n, (a, b)) in cables.iter().copied().enumerate() {
+            for letter in [a, b] {
+                if !letter.is_ascii_uppercase() {
+                    return Err(UhrError::InvalidCharacter(letter));
+                }
+
+                if wired[(letter as u8 - b'A') as usize] {
+                    return Err(UhrError::CharacterAlreadyWired(letter));
+                }
+
+                wired[(letter as u8 - b'A') as usize] = true;
+            }
+
+            a_plugs[n] = a;
+            b_plugs[n] = b;
+        }
+
+        let mut uhr = Self {
+            a_plugs,
+            b_plugs,
+            position: 0,
+            forward: Permutation::identity(),
+        };
+        uhr.set_position(position)?;
+
+        Ok(uhr)
+    }
+
+    pub fn position(&self) -> u8 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: u8) -> Result<(), UhrError> {
+        if position > 39 {
+            return Err(UhrError::InvalidPosition(position));
+        }
+
+        self.position = position;
+        self.forward = self.wire();
+
+        Ok(())
+    }
+
+    fn wire(&self) -> Permutation {
+        let p = self.position as usize;
+        let mut table = [' '; 26];
+
+        for (socket, letter) in self.a_plugs.iter().enumerate() {
+            let disk = (4 * socket + p) % 40;
+            let contact = (UHR_WIRING[disk] as usize + 40 - p) % 40;
+            let group = contact / 4;
+            let label = B_SOCKETS.iter().position(|g| *g == group).unwrap();
+
+            table[(*letter as u8 - b'A') as usize] = self.b_plugs[label];
+        }
+
+        for (label, letter) in self.b_plugs.iter().enumerate() {
+            let disk = (4 * B_SOCKETS[label] + p) % 40;
+            let back = UHR_WIRING.iter().position(|c| *c as usize == disk).unwrap();
+            let contact = (back + 40 - p) % 40;
+
+            table[(*letter as u8 - b'A') as usize] = self.a_plugs[contact / 4];
+        }
+
+        Permutation::from_fn(|c| match table[(c as u8 - b'A') as usize] {
+            ' ' => c,
+            mapped => mapped,
+        })
+        .expect("Uhr wiring is a permutation")
+    }
+}
+
+impl Stecker for Uhr {
+    fn transpose_in(&self, input: char) -> char {
+        self.forward.apply(input)
+    }
+
+    fn transpose_out(&self, input: char) -> char {
+        self.forward.inverse().apply(input)
+    }
+
+    fn permutation(&self) -> Permutation {
+        self.forward
+    }
+
+    /// The cables stay where they are; only the switch can be turned to
+    /// whichever position makes `permutation`.
+    fn restore(&mut self, permutation: &Permutation) -> Result<(), SnapshotError> {
+        let mut uhr = *self;
+
+        for position in 0..40 {
+            uhr.set_position(position).expect("0-39 are Uhr positions");
+
+            if uhr.forward == *permutation {
+                *self = uhr;
+                return Ok(());
+            }
+        }
+
+        Err(SnapshotError::Stecker)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::plugboard::Plugboard;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    const CABLES: [(char, char); 10] = [
+        ('A', 'B'),
+        ('C', 'D'),
+        ('E', 'F'),
+        ('G', 'H'),
+        ('I', 'J'),
+        ('K', 'L'),
+        ('M', 'N'),
+        ('O', 'P'),
+        ('Q', 'R'),
+        ('S', 'T'),
+    ];
+
+    #[test]
+    fn test_position_zero_is_plugboard() {
+        let uhr = Uhr::new(&CABLES, 0).unwrap();
+        let board = Plugboard::new(&CABLES).unwrap();
+
+        assert_eq!(uhr.permutation(), board.permutation());
+        assert_eq!(uhr.transpose_in('A'), 'B');
+        assert_eq!(uhr.transpose_out('A'), 'B');
+        assert_eq!(uhr.transpose_in('Z'), 'Z');
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_reciprocity() {
+        for position in 0..40 {
+            let uhr = Uhr::new(&CABLES, position).unwrap();
+            let perm = uhr.permutation();
+
+            // Only the twenty plugged letters move, and the stecker is only
+            // reciprocal every fourth position.
+            assert_eq!(perm.fixed_points().len(), 6);
+            assert_eq!(perm.is_involution(), position % 4 == 0);
+            assert_eq!(uhr.transpose_out(uhr.transpose_in('K')), 'K');
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_a_plugs_reach_b_plugs() {
+        let uhr = Uhr::new(&CABLES, 17).unwrap();
+        let a_letters: Vec<char> = CABLES.iter().map(|(a, _)| *a).collect();
+        let b_letters: Vec<char> = CABLES.iter().map(|(_, b)| *b).collect();
+
+        for (a, b) in CABLES.iter() {
+            assert!(b_letters.contains(&uhr.transpose_in(*a)));
+            assert!(a_letters.contains(&uhr.transpose_in(*b)));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Uhr::new(&CABLES[..9], 0), Err(UhrError::CableCount(9)));
+        assert_eq!(Uhr::new(&CABLES, 40), Err(UhrError::InvalidPosition(40)));
+
+        let mut cables = CABLES;
+        cables[3] = ('G', 'A');
+        assert_eq!(
+            Uhr::new(&cables, 0),
+            Err(UhrError::CharacterAlreadyWired('A'))
+        );
+
+        cables[3] = ('G', 'h');
+        assert_eq!(Uhr::new(&cables, 0), Err(UhrError::InvalidCharacter('h')));
+
+        let mut uhr = Uhr::new(&CABLES, 0).unwrap();
+        assert_eq!(uhr.set_position(52), Err(UhrError::InvalidPosition(52)));
+        assert_eq!(uhr.position(), 0);
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut uhr = Uhr::new(&CABLES, 0).unwrap();
+        let target = Uhr::new(&CABLES, 27).unwrap().permutation();
+
+        assert_eq!(uhr.restore(&target), Ok(()));
+        assert_eq!(uhr.position(), 27);
+        assert_eq!(
+            uhr.restore(&Permutation::shift(3)),
+            Err(SnapshotError::Stecker)
+        );
+        assert_eq!(uhr.position(), 27);
+    }
+}
@@ -0,0 +1,117 @@
+// tests/custom_wheels.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+// The registry and machine settings need an allocator.
+#![cfg(feature = "alloc")]
+
+use enigma_core::model::Model;
+use enigma_core::registry::{ReflectorSpec, Registry, RotorSpec};
+use enigma_core::rotors::{self, RotorEncode};
+use enigma_core::{plugboard::Plugboard, reflector, rotor, ArmyEnigma, Enigma};
+
+rotor!(pub CustomRotorI, "EKMFLGDQVZNTOWYHXUSPAIBRCJ", notches = "Q");
+rotor!(CustomRotorII, "AJDKSIRUXBLHWTMCQGZNPYFVOE", notches = "E");
+rotor!(
+    /// Rotor VI, with its two turnover notches.
+    CustomRotorVI,
+    "JPGVOUMFYQBENHZRDKASXLICTW",
+    notches = "ZM",
+    name = "VI",
+);
+rotor!(CustomStator, "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+reflector!(
+    #[derive(Default)]
+    CustomReflectorB,
+    "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+    name = "B",
+);
+
+#[test]
+fn test_custom_machine() {
+    let mut machine = ArmyEnigma::new(
+        CustomRotorI::new('A', 'A'),
+        CustomRotorII::new('A', 'A'),
+        rotors::RotorIII::new('A', 'A'),
+        CustomReflectorB,
+        None::<Plugboard>,
+    );
+
+    assert_eq!(machine.encipher_str("AAAAA").unwrap(), "BDZGO");
+}
+
+#[test]
+fn test_custom_rotor_matches_builtin() {
+    let mut custom = CustomRotorVI::new('C', 'X');
+    let mut builtin = rotors::RotorVI::new('C', 'X');
+
+    for _ in 0..52 {
+        assert_eq!(custom.permutation(), builtin.permutation());
+        assert_eq!(custom.at_notch(), builtin.at_notch());
+
+        custom.advance();
+        builtin.advance();
+    }
+}
+
+#[test]
+fn test_custom_names() {
+    let machine = ArmyEnigma::new(
+        CustomRotorVI::new('A', 'A'),
+        CustomRotorII::new('A', 'A'),
+        rotors::RotorIII::new('A', 'A'),
+        CustomReflectorB,
+        None::<Plugboard>,
+    );
+
+    assert_eq!(CustomStator::new('A', 'A').name(), "CustomStator");
+    assert_eq!(
+        machine.machine_settings().to_string(),
+        "B VI-CustomRotorII-III 01-01-01 AAA"
+    );
+}
+
+#[test]
+fn test_custom_rotor_without_notches() {
+    let mut rotor = CustomStator::new('A', 'A');
+
+    for _ in 0..26 {
+        assert!(!rotor.at_notch());
+        assert!(rotor.permutation().is_identity());
+        rotor.advance();
+    }
+}
+
+#[test]
+fn test_register_custom_wheels() {
+    let mut registry = Registry::builtin();
+
+    registry
+        .register_rotor(RotorSpec::new::<CustomRotorVI>(Model::EnigmaI, "VI-custom"))
+        .unwrap();
+    registry
+        .register_reflector(ReflectorSpec::fixed::<CustomReflectorB>(
+            Model::EnigmaI,
+            "UKW-B-custom",
+        ))
+        .unwrap();
+    assert!(registry
+        .register_rotor(RotorSpec::new::<CustomRotorI>(Model::EnigmaI, "I"))
+        .is_err());
+
+    let custom = registry.rotor_spec(Model::EnigmaI, "vi-custom").unwrap();
+    let builtin = registry.rotor_spec(Model::EnigmaI, "VI").unwrap();
+
+    assert_eq!(custom.wiring(), builtin.wiring());
+    assert_eq!(custom.notches(), "MZ");
+    assert!(registry
+        .reflector(Model::EnigmaI, "B-custom", 'A')
+        .unwrap()
+        .permutation()
+        .is_involution());
+}
@@ -0,0 +1,32 @@
+// tests/serde.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use enigma_core::reflectors::ReflectorB;
+use enigma_core::rotors::{RotorEncode, RotorI, RotorII, RotorIII};
+use enigma_core::snapshot::Snapshot;
+use enigma_core::{plugboard, ArmyEnigma, Enigma};
+
+#[test]
+fn test_snapshot_serde() {
+    let mut machine = ArmyEnigma::new(
+        RotorI::new('B', 'A'),
+        RotorII::new('C', 'A'),
+        RotorIII::new('D', 'A'),
+        ReflectorB,
+        plugboard!("AB CD"),
+    );
+    machine.keypress('A').unwrap();
+
+    let snapshot = machine.snapshot();
+    let json = serde_json::to_string(&snapshot).unwrap();
+
+    assert!(json.contains("\"stecker\":\"BADCEFGHIJKLMNOPQRSTUVWXYZ\""));
+    assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    assert!(serde_json::from_str::<Snapshot>(&json.replace("BADC", "BBDC")).is_err());
+}
@@ -9,35 +9,142 @@
 
 use proc_macro::TokenStream;
 use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, DeriveInput, Error, Token};
 
-fn check_keyspace(key_tokens: &str) {
+const ROTOR_FIELDS: &[(&str, &str)] = &[
+    ("ring_setting", "char"),
+    ("init_position", "char"),
+    ("init_offset", "u8"),
+    ("cur_offset", "u8"),
+];
+
+fn check_keyspace(key_tokens: &str) -> Result<(), String> {
     let mut keyspace: Vec<char> = key_tokens.chars().collect();
     let mut expected: Vec<char> = ('A'..='Z').collect();
 
     expected.sort();
     keyspace.sort();
 
-    assert_eq!(
-        expected, keyspace,
-        "Expected 26 unique characters in #[key_ordering(...)]"
-    );
+    if expected != keyspace {
+        return Err("Expected 26 unique characters A-Z in key ordering".into());
+    }
+
+    Ok(())
+}
+
+fn check_notches(notch_tokens: &str) -> Result<(), String> {
+    match notch_tokens.chars().find(|c| !c.is_ascii_uppercase()) {
+        Some(c) => Err(format!("'{c}' is not a valid notch, must be A-Z")),
+        None => Ok(()),
+    }
 }
 
-fn extract_attribute(tokens: &proc_macro2::TokenStream) -> String {
-    let attr_str = &tokens.to_string();
-    let to_trim: &[_] = &['(', ')'];
+fn check_involution(key_tokens: &str) -> Result<(), String> {
+    let wiring: Vec<char> = key_tokens.chars().collect();
+
+    for (input, output) in ('A'..='Z').zip(wiring.iter().copied()) {
+        let reflected = wiring[(output as u8 - b'A') as usize];
+
+        if reflected != input {
+            return Err(format!(
+                "Reflector wiring must be reciprocal, but '{input}' => '{output}' and '{output}' => '{reflected}'"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_fields(ast: &DeriveInput, required: &[(&str, &str)]) -> syn::Result<()> {
+    let fields = match &ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(named),
+            ..
+        }) => &named.named,
+        _ => {
+            return Err(Error::new_spanned(
+                &ast.ident,
+                "Expected a struct with named fields",
+            ))
+        }
+    };
+
+    for (name, ty) in required.iter() {
+        let field = fields
+            .iter()
+            .find(|field| field.ident.as_ref().is_some_and(|ident| ident == name));
+
+        match field {
+            Some(field) => {
+                let field_ty = &field.ty;
+                if quote!(#field_ty).to_string() != *ty {
+                    return Err(Error::new_spanned(
+                        field_ty,
+                        format!("Expected field `{name}` to have type `{ty}`"),
+                    ));
+                }
+            }
+            None => {
+                return Err(Error::new_spanned(
+                    &ast.ident,
+                    format!("Missing required field `{name}: {ty}`"),
+                ))
+            }
+        }
+    }
 
-    attr_str.trim_matches(to_trim).into()
+    Ok(())
+}
+
+fn find_attribute<'a>(ast: &'a DeriveInput, name: &str) -> Option<&'a syn::Attribute> {
+    ast.attrs.iter().find(|attr| attr.path.is_ident(name))
+}
+
+fn extract_attribute(attr: &syn::Attribute) -> syn::Result<String> {
+    let ident: syn::Ident = attr.parse_args()?;
+
+    Ok(ident.to_string())
+}
+
+/// The name a wheel goes by in key notation: `#[name(IV)]`, or
+/// `#[name("B-thin")]` for names that are not identifiers. Without the
+/// attribute, the type name less its `Rotor` or `Reflector` prefix.
+fn extract_name(ast: &DeriveInput, prefix: &str) -> syn::Result<String> {
+    match find_attribute(ast, "name") {
+        Some(attr) => match attr.parse_args::<syn::LitStr>() {
+            Ok(lit) => Ok(lit.value()),
+            Err(_) => extract_attribute(attr),
+        },
+        None => Ok(default_name(&ast.ident, prefix)),
+    }
+}
+
+fn default_name(ident: &syn::Ident, prefix: &str) -> String {
+    let ident = ident.to_string();
+
+    match ident.strip_prefix(prefix) {
+        Some(rest) if !rest.is_empty() => rest.to_owned(),
+        _ => ident,
+    }
+}
+
+fn extract_key_ordering(ast: &DeriveInput) -> syn::Result<(String, &syn::Attribute)> {
+    let attr = find_attribute(ast, "key_ordering")
+        .ok_or_else(|| Error::new_spanned(&ast.ident, "Missing #[key_ordering(...)] attribute"))?;
+    let key_ordering = extract_attribute(attr)?;
+
+    check_keyspace(&key_ordering).map_err(|msg| Error::new_spanned(attr, msg))?;
+
+    Ok((key_ordering, attr))
 }
 
 fn generate_key_mappings(
-    key_ordering: String,
+    key_ordering: &str,
 ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
     let mut transpose_in: proc_macro2::TokenStream = quote!();
     let mut transpose_out: proc_macro2::TokenStream = quote!();
 
-    check_keyspace(&key_ordering);
-
     for (ordering_char, mapped_char) in key_ordering.chars().zip('A'..='Z') {
         transpose_in.extend(quote! {
             #mapped_char => #ordering_char,
@@ -50,27 +157,45 @@ fn generate_key_mappings(
     (transpose_in, transpose_out)
 }
 
-#[proc_macro_derive(RotorEncode, attributes(key_ordering, notches))]
+#[proc_macro_derive(RotorEncode, attributes(key_ordering, notches, name))]
 pub fn rotor_encode_derive(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
+    let ast = parse_macro_input!(input as DeriveInput);
 
     impl_rotor_encode(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
-fn impl_rotor_encode(ast: &syn::DeriveInput) -> TokenStream {
+fn impl_rotor_encode(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &ast.ident;
-    let mut key_ordering: String = "empty".into();
-    let mut notch_tokens: String = "empty".into();
-
-    for attr in ast.attrs.iter() {
-        let attr_name = attr.path.get_ident().unwrap().to_string();
-        match &*attr_name {
-            "key_ordering" => key_ordering = extract_attribute(&attr.tokens),
-            "notches" => notch_tokens = extract_attribute(&attr.tokens),
-            _ => {}
-        };
-    }
 
+    check_fields(ast, ROTOR_FIELDS)?;
+
+    let (key_ordering, _) = extract_key_ordering(ast)?;
+    let display_name = extract_name(ast, "Rotor")?;
+    let notch_tokens = match find_attribute(ast, "notches") {
+        Some(attr) => {
+            let notch_tokens = extract_attribute(attr)?;
+            check_notches(&notch_tokens).map_err(|msg| Error::new_spanned(attr, msg))?;
+            notch_tokens
+        }
+        None => String::new(),
+    };
+
+    Ok(generate_rotor_impl(
+        name,
+        &display_name,
+        &key_ordering,
+        &notch_tokens,
+    ))
+}
+
+fn generate_rotor_impl(
+    name: &syn::Ident,
+    display_name: &str,
+    key_ordering: &str,
+    notch_tokens: &str,
+) -> proc_macro2::TokenStream {
     let (transpose_in, transpose_out) = generate_key_mappings(key_ordering);
 
     let mut notch_map: proc_macro2::TokenStream = quote!();
@@ -81,144 +206,461 @@ fn impl_rotor_encode(ast: &syn::DeriveInput) -> TokenStream {
         });
     }
 
-    let gen = quote! {
-        impl RotorEncode for #name {
-            fn new(ring_setting: char, init_position: char) -> Self {
-                Self {
-                    ring_setting: ring_setting,
-                    init_position: init_position,
-                    init_offset: (ring_setting as u8) - 65,
-                    cur_offset: (init_position as u8) - 65,
+    quote! {
+        const _: () = {
+            use ::enigma_core::rotors::{_apply_offset, RotorEncode};
+
+            impl RotorEncode for #name {
+                fn new(ring_setting: char, init_position: char) -> Self {
+                    Self {
+                        ring_setting: ring_setting,
+                        init_position: init_position,
+                        init_offset: (ring_setting as u8) - 65,
+                        cur_offset: (init_position as u8) - 65,
+                    }
                 }
-            }
 
-            fn _shift_input(&self, input: char) -> char {
-                let offset: i8 = -self.get_offset();
-                let input_val = input as i8;
+                fn _shift_input(&self, input: char) -> char {
+                    let offset: i8 = -self.get_offset();
+                    let input_val = input as i8;
 
-                _apply_offset(input_val + offset)
-            }
+                    _apply_offset(input_val + offset)
+                }
 
-            fn _shift_output(&self, output: char) -> char {
-                let offset: i8 = self.get_offset();
-                let output_val = output as i8;
+                fn _shift_output(&self, output: char) -> char {
+                    let offset: i8 = self.get_offset();
+                    let output_val = output as i8;
 
-                _apply_offset(output_val + offset)
-            }
+                    _apply_offset(output_val + offset)
+                }
 
-            fn transpose_in(&self, input: char) -> char {
-                let computed = match self._shift_input(input) {
-                    #transpose_in
-                    _  => ' ',
-                };
+                fn transpose_in(&self, input: char) -> char {
+                    let computed = match self._shift_input(input) {
+                        #transpose_in
+                        _  => ' ',
+                    };
 
-                self._shift_output(computed)
-            }
+                    self._shift_output(computed)
+                }
 
-            fn transpose_out(&self, input: char) -> char {
-                let computed = match self._shift_input(input) {
-                    #transpose_out
-                    _  => ' ',
-                };
+                fn transpose_out(&self, input: char) -> char {
+                    let computed = match self._shift_input(input) {
+                        #transpose_out
+                        _  => ' ',
+                    };
 
-                self._shift_output(computed)
-            }
+                    self._shift_output(computed)
+                }
 
-            fn at_notch(&self) -> bool {
-                match (65 + self.cur_offset) as char {
-                    #notch_map
-                    _ => false,
+                fn at_notch(&self) -> bool {
+                    match (65 + self.cur_offset) as char {
+                        #notch_map
+                        _ => false,
+                    }
                 }
-            }
 
-            fn advance(&mut self)  {
-                let step = 1;
+                fn advance(&mut self)  {
+                    let step = 1;
 
-                self.cur_offset = match self.cur_offset + step > 25 {
-                    true => self.cur_offset + step - 25,
-                    false => self.cur_offset + step,
+                    self.cur_offset = match self.cur_offset + step > 25 {
+                        true => self.cur_offset + step - 26,
+                        false => self.cur_offset + step,
+                    }
                 }
-            }
 
-            fn ring_setting(&self) -> char {
-                self.ring_setting
-            }
+                fn set_ring_setting(&mut self, ring_setting: char) {
+                    self.ring_setting = ring_setting;
+                    self.init_offset = (ring_setting as u8) - 65;
+                }
 
-            fn init_position(&self) -> char {
-                self.init_position
-            }
+                fn set_init_position(&mut self, init_position: char) {
+                    self.init_position = init_position;
+                }
 
-            fn position(&self) -> char {
-                self._shift_input(self.ring_setting)
-            }
+                fn set_position(&mut self, position: char) {
+                    self.cur_offset = (position as u8) - 65;
+                }
 
-            fn get_offset(&self) -> i8 {
-                if self.cur_offset == self.init_offset {
-                    return 0;
-                } else if self.cur_offset < self.init_offset {
-                    return (self.init_offset - self.cur_offset) as i8;
-                } else {
-                    return ((self.init_offset + 26) - self.cur_offset) as i8;
+                fn name(&self) -> &'static str {
+                    #display_name
+                }
+
+                fn ring_setting(&self) -> char {
+                    self.ring_setting
+                }
+
+                fn init_position(&self) -> char {
+                    self.init_position
                 }
-            }
-        }
-    };
 
-    gen.into()
+                fn position(&self) -> char {
+                    self._shift_input(self.ring_setting)
+                }
+
+                fn get_offset(&self) -> i8 {
+                    if self.cur_offset == self.init_offset {
+                        return 0;
+                    } else if self.cur_offset < self.init_offset {
+                        return (self.init_offset - self.cur_offset) as i8;
+                    } else {
+                        return ((self.init_offset + 26) - self.cur_offset) as i8;
+                    }
+                }
+            }
+        };
+    }
 }
 
-#[proc_macro_derive(Reflector, attributes(key_ordering))]
+#[proc_macro_derive(Reflector, attributes(key_ordering, name))]
 pub fn reflector_derive(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
+    let ast = parse_macro_input!(input as DeriveInput);
 
     impl_reflector(&ast)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
-fn impl_reflector(ast: &syn::DeriveInput) -> TokenStream {
+fn impl_reflector(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &ast.ident;
-    let mut key_ordering: String = "empty".into();
+    let (key_ordering, attr) = extract_key_ordering(ast)?;
 
-    for attr in ast.attrs.iter() {
-        let attr_name = attr.path.get_ident().unwrap().to_string();
-        if &*attr_name == "key_ordering" {
-            key_ordering = extract_attribute(&attr.tokens)
-        }
-    }
+    check_involution(&key_ordering).map_err(|msg| Error::new_spanned(attr, msg))?;
+
+    let display_name = extract_name(ast, "Reflector")?;
+
+    Ok(generate_reflector_impl(name, &display_name, &key_ordering))
+}
 
+fn generate_reflector_impl(
+    name: &syn::Ident,
+    display_name: &str,
+    key_ordering: &str,
+) -> proc_macro2::TokenStream {
     let (transpose, _) = generate_key_mappings(key_ordering);
 
-    let gen = quote! {
-        impl Reflector for #name {
+    quote! {
+        impl ::enigma_core::reflectors::Reflector for #name {
             fn transpose(&self, input: char) -> char {
                 match input {
                     #transpose
                     _  => ' ',
                 }
             }
+
+            fn name(&self) -> &'static str {
+                #display_name
+            }
         }
-    };
+    }
+}
+
+struct WheelDefinition {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    name: syn::Ident,
+    key_ordering: syn::LitStr,
+    notches: Option<syn::LitStr>,
+    display_name: Option<syn::LitStr>,
+}
+
+impl Parse for WheelDefinition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let name = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let key_ordering = input.parse()?;
+        let mut notches = None;
+        let mut display_name = None;
+
+        while input.parse::<Token![,]>().is_ok() && !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+
+            match &*key.to_string() {
+                "notches" if notches.is_none() => notches = Some(input.parse()?),
+                "notches" => return Err(Error::new_spanned(key, "Duplicate `notches` argument")),
+                "name" if display_name.is_none() => display_name = Some(input.parse()?),
+                "name" => return Err(Error::new_spanned(key, "Duplicate `name` argument")),
+                _ => {
+                    return Err(Error::new_spanned(
+                        key,
+                        "Expected `notches = \"...\"` or `name = \"...\"`",
+                    ))
+                }
+            }
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("Unexpected tokens after wheel definition"));
+        }
+
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            key_ordering,
+            notches,
+            display_name,
+        })
+    }
+}
+
+/// Define a rotor type and its `RotorEncode` impl in one line:
+///
+/// ```ignore
+/// rotor!(pub RotorVI, "JPGVOUMFYQBENHZRDKASXLICTW", notches = "ZM");
+/// rotor!(pub RotorKI, "LPGSZMHAEOQKVXRFYBUTNICJDW", notches = "Y", name = "I");
+/// ```
+#[proc_macro]
+pub fn rotor(input: TokenStream) -> TokenStream {
+    let def = parse_macro_input!(input as WheelDefinition);
+
+    impl_rotor(&def)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn impl_rotor(def: &WheelDefinition) -> syn::Result<proc_macro2::TokenStream> {
+    let WheelDefinition {
+        attrs, vis, name, ..
+    } = def;
+    let key_ordering = def.key_ordering.value();
+    let notch_tokens = def.notches.as_ref().map(|n| n.value()).unwrap_or_default();
+
+    check_keyspace(&key_ordering).map_err(|msg| Error::new_spanned(&def.key_ordering, msg))?;
+
+    if let Some(notches) = &def.notches {
+        check_notches(&notch_tokens).map_err(|msg| Error::new_spanned(notches, msg))?;
+    }
+
+    let display_name = def
+        .display_name
+        .as_ref()
+        .map(|n| n.value())
+        .unwrap_or_else(|| default_name(name, "Rotor"));
+    let rotor_impl = generate_rotor_impl(name, &display_name, &key_ordering, &notch_tokens);
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #name {
+            ring_setting: char,
+            init_position: char,
+            init_offset: u8,
+            cur_offset: u8,
+        }
+
+        #rotor_impl
+    })
+}
+
+/// Define a reflector type and its `Reflector` impl in one line:
+///
+/// ```ignore
+/// reflector!(pub ReflectorB, "YRUHQSLDPXNGOKMIEBFZCWVJAT");
+/// ```
+#[proc_macro]
+pub fn reflector(input: TokenStream) -> TokenStream {
+    let def = parse_macro_input!(input as WheelDefinition);
+
+    impl_reflector_definition(&def)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn impl_reflector_definition(def: &WheelDefinition) -> syn::Result<proc_macro2::TokenStream> {
+    let WheelDefinition {
+        attrs, vis, name, ..
+    } = def;
+    let key_ordering = def.key_ordering.value();
+
+    if let Some(notches) = &def.notches {
+        return Err(Error::new_spanned(
+            notches,
+            "Reflectors do not have notches",
+        ));
+    }
+
+    check_keyspace(&key_ordering)
+        .and_then(|_| check_involution(&key_ordering))
+        .map_err(|msg| Error::new_spanned(&def.key_ordering, msg))?;
+
+    let display_name = def
+        .display_name
+        .as_ref()
+        .map(|n| n.value())
+        .unwrap_or_else(|| default_name(name, "Reflector"));
+    let reflector_impl = generate_reflector_impl(name, &display_name, &key_ordering);
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #name;
+
+        #reflector_impl
+    })
+}
+
+enum PlugboardDefinition {
+    Pairs(Vec<(syn::LitChar, syn::LitChar)>),
+    Compact(syn::LitStr),
+}
+
+impl Parse for PlugboardDefinition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::LitStr) {
+            let compact = input.parse()?;
+
+            if !input.is_empty() {
+                return Err(input.error("Unexpected tokens after plugboard wiring"));
+            }
 
-    gen.into()
+            return Ok(PlugboardDefinition::Compact(compact));
+        }
+
+        let mut pairs = Vec::new();
+
+        while !input.is_empty() {
+            let left = input.parse()?;
+            input.parse::<Token![=>]>()?;
+            let right = input.parse()?;
+            pairs.push((left, right));
+
+            if input.is_empty() {
+                break;
+            }
+
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(PlugboardDefinition::Pairs(pairs))
+    }
+}
+
+fn check_plug(plug: char, wired: &[char]) -> Result<(), String> {
+    if !plug.is_ascii_uppercase() {
+        return Err(format!("{plug:?} is not a valid character, must be A-Z"));
+    }
+
+    if wired.contains(&plug) {
+        return Err(format!("{plug:?} already wired to plugboard"));
+    }
+
+    Ok(())
+}
+
+fn check_cable(left: char, right: char, wired: &mut Vec<char>) -> Result<(), String> {
+    check_plug(left, wired)?;
+    check_plug(right, wired)?;
+
+    if left == right {
+        return Err(format!("{left:?} cannot be wired to itself"));
+    }
+
+    wired.push(left);
+    wired.push(right);
+
+    Ok(())
+}
+
+/// Build a `Plugboard` whose wiring is checked at compile time, either from
+/// pairs or from the key-sheet notation:
+///
+/// ```ignore
+/// let board = plugboard! { 'A' => 'E', 'F' => 'J' };
+/// let board = plugboard!("AE FJ");
+/// ```
+#[proc_macro]
+pub fn plugboard(input: TokenStream) -> TokenStream {
+    let def = parse_macro_input!(input as PlugboardDefinition);
+
+    impl_plugboard(&def)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn impl_plugboard(def: &PlugboardDefinition) -> syn::Result<proc_macro2::TokenStream> {
+    let mut wired = Vec::new();
+    let mut cables = Vec::new();
+
+    match def {
+        PlugboardDefinition::Pairs(pairs) => {
+            for (left, right) in pairs.iter() {
+                let (l, r) = (left.value(), right.value());
+
+                check_plug(l, &wired).map_err(|msg| Error::new_spanned(left, msg))?;
+                check_cable(l, r, &mut wired).map_err(|msg| Error::new_spanned(right, msg))?;
+                cables.push((l, r));
+            }
+        }
+        PlugboardDefinition::Compact(compact) => {
+            for pair in compact.value().split_whitespace() {
+                let plugs: Vec<char> = pair.chars().collect();
+
+                match plugs[..] {
+                    [l, r] => {
+                        check_cable(l, r, &mut wired)
+                            .map_err(|msg| Error::new_spanned(compact, msg))?;
+                        cables.push((l, r));
+                    }
+                    _ => {
+                        return Err(Error::new_spanned(
+                            compact,
+                            format!("{pair:?} is not a pair of letters"),
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    let cables = cables.iter().map(|(l, r)| quote!((#l, #r)));
+
+    Ok(quote! {
+        ::enigma_core::plugboard::Plugboard::new(&[#(#cables),*])
+            .expect("plugboard wiring is checked at compile time")
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::check_keyspace;
+    use super::{check_involution, check_keyspace, check_notches};
 
     #[test]
-    #[should_panic(expected = "Expected 26 unique characters in #[key_ordering(...)]")]
     fn test_check_keyspace_too_short() {
         let input: String = ('A'..='M').collect();
-        check_keyspace(&input);
+        assert_eq!(
+            check_keyspace(&input),
+            Err("Expected 26 unique characters A-Z in key ordering".into())
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Expected 26 unique characters in #[key_ordering(...)]")]
     fn test_check_keyspace_duplicate() {
         let mut input: String = ('A'..='Z').collect();
         input.push('Z');
 
         // But has duplicates!
-        check_keyspace(&input);
+        assert_eq!(
+            check_keyspace(&input),
+            Err("Expected 26 unique characters A-Z in key ordering".into())
+        );
+    }
+
+    #[test]
+    fn test_check_notches() {
+        assert_eq!(check_notches("ZM"), Ok(()));
+        assert_eq!(
+            check_notches("Zm"),
+            Err("'m' is not a valid notch, must be A-Z".into())
+        );
+    }
+
+    #[test]
+    fn test_check_involution() {
+        assert_eq!(check_involution("YRUHQSLDPXNGOKMIEBFZCWVJAT"), Ok(()));
+        assert_eq!(
+            check_involution("EKMFLGDQVZNTOWYHXUSPAIBRCJ"),
+            Err("Reflector wiring must be reciprocal, but 'A' => 'E' and 'E' => 'L'".into())
+        );
     }
 }
@@ -0,0 +1,13 @@
+use enigma_macros::RotorEncode;
+
+#[derive(RotorEncode)]
+#[key_ordering(EKMFLGDQVZNTOWYHXUSPAIBRCJ)]
+#[notches(Qz)]
+pub struct RotorI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use enigma_macros::RotorEncode;
+
+#[derive(RotorEncode)]
+#[notches(Q)]
+pub struct RotorI {
+    ring_setting: char,
+    init_position: char,
+    init_offset: u8,
+    cur_offset: u8,
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+use enigma_macros::plugboard;
+
+fn main() {
+    let _board = plugboard!("AB CD E1");
+}
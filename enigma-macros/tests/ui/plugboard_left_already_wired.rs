@@ -0,0 +1,8 @@
+use enigma_macros::plugboard;
+
+fn main() {
+    let _board = plugboard! {
+        'A' => 'F',
+        'A' => 'E'
+    };
+}
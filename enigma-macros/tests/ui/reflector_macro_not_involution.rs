@@ -0,0 +1,5 @@
+use enigma_macros::reflector;
+
+reflector!(ReflectorI, "EKMFLGDQVZNTOWYHXUSPAIBRCJ");
+
+fn main() {}
@@ -0,0 +1,6 @@
+use enigma_macros::Reflector;
+
+#[derive(Reflector)]
+pub struct ReflectorI;
+
+fn main() {}
@@ -0,0 +1,7 @@
+use enigma_macros::Reflector;
+
+#[derive(Reflector)]
+#[key_ordering(EKMFLGDQVZNTOWYHXUSPAIBRCJ)]
+pub struct ReflectorI;
+
+fn main() {}
@@ -0,0 +1,5 @@
+use enigma_macros::rotor;
+
+rotor!(RotorI, "EKMFLGDQVZNTOWYHXUSPAIBRC", notches = "Q");
+
+fn main() {}
@@ -0,0 +1,5 @@
+use enigma_macros::rotor;
+
+rotor!(RotorI, "EKMFLGDQVZNTOWYHXUSPAIBRCJ", notches = "Q1");
+
+fn main() {}
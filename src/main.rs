@@ -10,31 +10,28 @@
 mod ui;
 
 use anyhow::Result;
+use std::path::PathBuf;
 
 use ui::generic::UiAgent;
 use ui::tui::Tui;
 
-use std::collections::HashMap;
-
-use enigma_core::plugboard::Plugboard;
 use enigma_core::reflectors;
 use enigma_core::rotors::{self, RotorEncode};
-use enigma_core::{ArmyEnigma, plugboard};
+use enigma_core::{plugboard, ArmyEnigma};
 
 fn main() -> Result<()> {
     let mut machine = ArmyEnigma::new(
         rotors::RotorIII::new('G', 'E'),
         rotors::RotorII::new('E', 'H'),
         rotors::RotorIV::new('W', 'R'),
-        reflectors::ReflectorC{},
-        plugboard! {
-            'E' => 'R',
-            'S' => 'A',
-            'T' => 'Z'
-        },
+        reflectors::ReflectorC {},
+        plugboard!("ER SA TZ"),
     );
 
-    let handle = Tui::new(&mut machine).unwrap();
+    // Ctrl-S writes the session here; it is picked back up on the next start.
+    let session_path = PathBuf::from("enigma-session.json");
+
+    let handle = Tui::new(&mut machine, session_path).unwrap();
     handle.start()?;
 
     Ok(())
@@ -15,5 +15,4 @@ pub trait UiAgent {
 
 pub enum ApplicationExitReason {
     UserExit,
-    Reload(usize),
 }
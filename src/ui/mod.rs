@@ -8,5 +8,6 @@
 // terms.
 
 pub mod generic;
+pub mod session;
 pub mod state;
 pub mod tui;
@@ -0,0 +1,46 @@
+// src/ui/session.rs
+//
+// Copyright (c) 2021
+// Jeff Nettleton
+//
+// Licensed under the MIT license (http://opensource.org/licenses/MIT). This
+// file may not be copied, modified, or distributed except according to those
+// terms.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use enigma_core::snapshot::Snapshot;
+
+/// Everything needed to pick a session back up: the machine itself and the
+/// text typed so far.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub machine: Snapshot,
+    pub input: String,
+    pub output: String,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read session from {}", path.display()))?;
+        let session = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse session in {}", path.display()))?;
+
+        Ok(Some(session))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write session to {}", path.display()))
+    }
+}
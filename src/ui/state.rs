@@ -7,35 +7,101 @@
 // file may not be copied, modified, or distributed except according to those
 // terms.
 
+use enigma_core::message::{self, Layout};
+use enigma_core::settings::MachineSettings;
+
 pub struct MachineState {
     pub machine_state: String,
-    pub input_state: String,
-    pub output_state: String,
+    /// The letters typed so far, without any grouping.
+    pub input: String,
+    pub output: String,
+    /// A note for the user, such as whether the session was saved.
+    pub status: Option<String>,
+    layout: Layout,
 }
 
 impl MachineState {
-    pub fn new(rotor_positions: &[char]) -> Self {
+    pub fn new(settings: &MachineSettings) -> Self {
+        MachineState::resume(settings, "", "")
+    }
+
+    /// Pick up where a saved session left off. Sessions saved with their
+    /// text already grouped are read back to the bare letters.
+    pub fn resume(settings: &MachineSettings, input: &str, output: &str) -> Self {
         Self {
-            machine_state: MachineState::build_rotor_string(rotor_positions),
-            input_state: "".into(),
-            output_state: "".into(),
+            machine_state: MachineState::build_setup_string(settings),
+            input: message::raw(input),
+            output: message::raw(output),
+            status: None,
+            layout: Layout::default(),
         }
     }
 
-    pub fn update(&mut self, input: char, output: char, rotor_positions: &[char]) {
-        self.machine_state = MachineState::build_rotor_string(rotor_positions);
+    pub fn update(&mut self, input: char, output: char, settings: &MachineSettings) {
+        self.machine_state = MachineState::build_setup_string(settings);
 
-        self.input_state.push_str(&format!("{input}"));
-        self.output_state.push_str(&format!("{output}"));
+        self.input.push(input);
+        self.output.push(output);
+    }
 
-        let raw_chars: Vec<char> = self.input_state.chars().filter(|x| *x != ' ').collect();
-        if raw_chars.len() % 5 == 0 {
-            self.input_state.push(' ');
-            self.output_state.push(' ');
+    pub fn setup_heading(&self) -> String {
+        match &self.status {
+            Some(status) => format!("Machine setup - {status}"),
+            None => "Machine setup".into(),
         }
     }
 
-    fn build_rotor_string(rotor_positions: &[char]) -> String {
-        rotor_positions.iter().map(|x| format!(" {}", *x)).collect()
+    pub fn input_state(&self) -> String {
+        self.layout.format(&self.input)
+    }
+
+    pub fn output_state(&self) -> String {
+        self.layout.format(&self.output)
+    }
+
+    /// The letter and group counts that head a radio message.
+    pub fn output_heading(&self) -> String {
+        format!(
+            "Output - {} letters, {} groups",
+            self.output.len(),
+            self.layout.group_count(&self.output)
+        )
+    }
+
+    fn build_setup_string(settings: &MachineSettings) -> String {
+        let mut lines = vec![format!("Key        {settings}"), String::new()];
+
+        match settings.reflector_position {
+            Some(position) => lines.push(format!(
+                "Reflector  {}  window {position}",
+                settings.reflector
+            )),
+            None => lines.push(format!("Reflector  {}", settings.reflector)),
+        }
+
+        for (n, rotor) in settings.rotors.iter().enumerate() {
+            let notch = if rotor.at_notch { "  at notch" } else { "" };
+
+            lines.push(format!(
+                "Rotor {}    {:<5} ring {:02}  window {}{notch}",
+                n + 1,
+                rotor.name,
+                rotor.ring_number(),
+                rotor.position,
+            ));
+        }
+
+        let plugboard = match settings.plugboard_pairs() {
+            Some(pairs) if pairs.is_empty() => "none".into(),
+            Some(pairs) => pairs
+                .iter()
+                .map(|(left, right)| format!("{left}{right}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => settings.stecker.wiring(),
+        };
+        lines.push(format!("Plugboard  {plugboard}"));
+
+        lines.join("\n")
     }
 }
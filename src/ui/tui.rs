@@ -8,9 +8,10 @@
 // terms.
 
 use anyhow::{Context, Result};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::execute;
 use std::io;
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -23,6 +24,7 @@ use tui::widgets::{Block, BorderType, Borders, Paragraph, Wrap};
 use tui::Terminal;
 
 use crate::ui::generic::{ApplicationExitReason, UiAgent};
+use crate::ui::session::Session;
 use crate::ui::state::MachineState;
 use enigma_core::reflectors::Reflector;
 use enigma_core::rotors::RotorEncode;
@@ -31,10 +33,14 @@ use enigma_core::{plugboard, ArmyEnigma, Enigma};
 pub struct Tui<'a, A, B, C, D> {
     machine: &'a mut ArmyEnigma<A, B, C, D, plugboard::Plugboard>,
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    session_path: PathBuf,
 }
 
 impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Tui<'a, A, B, C, D> {
-    pub fn new(machine: &'a mut ArmyEnigma<A, B, C, D, plugboard::Plugboard>) -> Result<Self> {
+    pub fn new(
+        machine: &'a mut ArmyEnigma<A, B, C, D, plugboard::Plugboard>,
+        session_path: PathBuf,
+    ) -> Result<Self> {
         let mut stdout = io::stdout();
 
         execute!(stdout, event::EnableMouseCapture)?;
@@ -45,7 +51,44 @@ impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Tui<'a, A
         crossterm::terminal::enable_raw_mode()?;
         terminal.hide_cursor()?;
 
-        Ok(Tui { machine, terminal })
+        Ok(Tui {
+            machine,
+            terminal,
+            session_path,
+        })
+    }
+}
+
+impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> Tui<'a, A, B, C, D> {
+    /// Put the machine back as the saved session left it. The machine is
+    /// untouched if the session doesn't fit it.
+    fn resume_session(&mut self) -> Result<Option<MachineState>> {
+        let session = match Session::load(&self.session_path)? {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+
+        self.machine.restore(&session.machine).with_context(|| {
+            format!(
+                "Session in {} doesn't fit this machine",
+                self.session_path.display()
+            )
+        })?;
+
+        Ok(Some(MachineState::resume(
+            &self.machine.machine_settings(),
+            &session.input,
+            &session.output,
+        )))
+    }
+}
+
+impl<'a, A, B, C, D> Drop for Tui<'a, A, B, C, D> {
+    /// Hand the terminal back as it was, however the TUI exits.
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+        let _ = execute!(io::stdout(), event::DisableMouseCapture);
+        let _ = self.terminal.show_cursor();
     }
 }
 
@@ -53,7 +96,17 @@ impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> UiAgent
     for Tui<'a, A, B, C, D>
 {
     fn start(mut self) -> Result<ApplicationExitReason> {
-        let mut state = MachineState::new(&self.machine.settings());
+        // A session that can't be read or doesn't fit the machine is set
+        // aside rather than keeping the TUI from starting.
+        let mut state = match self.resume_session() {
+            Ok(Some(state)) => state,
+            Ok(None) => MachineState::new(&self.machine.machine_settings()),
+            Err(e) => {
+                let mut state = MachineState::new(&self.machine.machine_settings());
+                state.status = Some(format!("Could not resume session: {e:#}"));
+                state
+            }
+        };
         let (tx, rx) = mpsc::channel();
         let tick_rate = Duration::from_millis(200);
 
@@ -67,10 +120,6 @@ impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> UiAgent
                         if tx.send(Interrupt::KeyPressed(key)).is_err() {
                             return;
                         }
-                    } else if let Event::Mouse(mouse) = event {
-                        if tx.send(Interrupt::MouseEvent(mouse)).is_err() {
-                            return;
-                        }
                     }
                 }
                 if last_tick.elapsed() > tick_rate {
@@ -96,7 +145,19 @@ impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> UiAgent
                     // reset
                     KeyCode::Char('r') if event.modifiers == KeyModifiers::CONTROL => {
                         self.machine.reset();
-                        state = MachineState::new(&self.machine.settings());
+                        state = MachineState::new(&self.machine.machine_settings());
+                    }
+                    // save the session
+                    KeyCode::Char('s') if event.modifiers == KeyModifiers::CONTROL => {
+                        let session = Session {
+                            machine: self.machine.snapshot(),
+                            input: state.input.clone(),
+                            output: state.output.clone(),
+                        };
+                        state.status = Some(match session.save(&self.session_path) {
+                            Ok(()) => "Session saved".into(),
+                            Err(e) => format!("{e:#}"),
+                        });
                     }
                     KeyCode::Char(c) => match c {
                         'A'..='Z' | 'a'..='z' => {
@@ -105,8 +166,8 @@ impl<'a, A: RotorEncode, B: RotorEncode, C: RotorEncode, D: Reflector> UiAgent
                                 false => c,
                             };
 
-                            let o = self.machine.keypress(i).unwrap();
-                            state.update(i, o, &self.machine.settings());
+                            let o = self.machine.keypress(i)?;
+                            state.update(i, o, &self.machine.machine_settings());
                         }
                         _ => {}
                     },
@@ -136,12 +197,17 @@ fn draw_layout_and_subcomponents<K: Backend>(f: &mut Frame<K>, state: &MachineSt
         {
             draw_text(
                 f,
-                "Machine setup".into(),
+                state.setup_heading(),
                 Some(&state.machine_state),
                 left_plane,
             );
-            draw_text(f, "Input".into(), Some(&state.input_state), input_plane);
-            draw_text(f, "Output".into(), Some(&state.output_state), output_plane);
+            draw_text(f, "Input".into(), Some(&state.input_state()), input_plane);
+            draw_text(
+                f,
+                state.output_heading(),
+                Some(&state.output_state()),
+                output_plane,
+            );
         } else {
             panic!("Failed to draw vertically-split right plane");
         }
@@ -171,18 +237,22 @@ fn draw_text<K: Backend>(
                 .add_modifier(Modifier::BOLD),
         );
     let default_string = String::new();
-    let text = vec![Spans::from(Span::styled(
-        format!("\n {} ", text_to_write.unwrap_or(&default_string)),
-        Style::default()
-            .fg(Color::LightBlue)
-            .add_modifier(Modifier::BOLD),
-    ))];
+    let style = Style::default()
+        .fg(Color::LightBlue)
+        .add_modifier(Modifier::BOLD);
+    let text: Vec<Spans> = std::iter::once(Spans::default())
+        .chain(
+            text_to_write
+                .unwrap_or(&default_string)
+                .lines()
+                .map(|line| Spans::from(Span::styled(format!(" {line} "), style))),
+        )
+        .collect();
     let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
     f.render_widget(paragraph, area);
 }
 
 enum Interrupt {
     KeyPressed(KeyEvent),
-    MouseEvent(MouseEvent),
     IntervalElapsed,
 }